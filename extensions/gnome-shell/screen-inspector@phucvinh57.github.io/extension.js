@@ -0,0 +1,71 @@
+import Gio from 'gi://Gio';
+import GLib from 'gi://GLib';
+import Meta from 'gi://Meta';
+import {Extension} from 'resource:///org/gnome/shell/extensions/extension.js';
+
+const OBJECT_PATH = '/io/github/phucvinh57/ScreenInspector';
+const INTERFACE_FILE = 'io.github.phucvinh57.ScreenInspector.xml';
+
+class ScreenInspector {
+    GetFocusedWindow() {
+        const window = global.display.get_focus_window();
+        if (!window)
+            return {};
+
+        const properties = {
+            id: new GLib.Variant('t', window.get_id()),
+            title: new GLib.Variant('s', window.get_title() ?? ''),
+            fullscreen: new GLib.Variant('b', window.is_fullscreen()),
+            maximized: new GLib.Variant('b', window.get_maximized() === Meta.MaximizeFlags.BOTH),
+            hidden: new GLib.Variant('b', window.minimized),
+            above: new GLib.Variant('b', window.is_above()),
+            demands_attention: new GLib.Variant('b', window.demands_attention),
+            xwayland: new GLib.Variant('b', window.get_client_type() === Meta.WindowClientType.X11),
+        };
+
+        const wmClass = window.get_wm_class();
+        if (wmClass)
+            properties.wm_class = new GLib.Variant('s', wmClass);
+        const wmClassInstance = window.get_wm_class_instance();
+        if (wmClassInstance)
+            properties.wm_class_instance = new GLib.Variant('s', wmClassInstance);
+
+        const pid = window.get_pid();
+        if (pid > 0)
+            properties.pid = new GLib.Variant('u', pid);
+
+        // Windows on all workspaces get the active one
+        const workspace = window.is_on_all_workspaces()
+            ? global.workspace_manager.get_active_workspace()
+            : window.get_workspace();
+        if (workspace) {
+            const index = workspace.index();
+            properties.workspace_index = new GLib.Variant('u', index);
+            properties.workspace_name = new GLib.Variant('s', Meta.prefs_get_workspace_name(index));
+        }
+
+        const rect = window.get_frame_rect();
+        properties.x = new GLib.Variant('i', rect.x);
+        properties.y = new GLib.Variant('i', rect.y);
+        properties.width = new GLib.Variant('u', rect.width);
+        properties.height = new GLib.Variant('u', rect.height);
+
+        return properties;
+    }
+}
+
+export default class ScreenInspectorExtension extends Extension {
+    enable() {
+        const [, xml] = this.dir.get_child(INTERFACE_FILE).load_contents(null);
+        this._dbus = Gio.DBusExportedObject.wrapJSObject(
+            new TextDecoder().decode(xml),
+            new ScreenInspector()
+        );
+        this._dbus.export(Gio.DBus.session, OBJECT_PATH);
+    }
+
+    disable() {
+        this._dbus?.unexport();
+        this._dbus = null;
+    }
+}
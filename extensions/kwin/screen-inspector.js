@@ -0,0 +1,76 @@
+// Loaded by screen-inspector through org.kde.KWin /Scripting. Reports the active window to the
+// process that loaded it, as JSON, each time the focus or the caption of the active window changes.
+// Works with the Plasma 6 (windowActivated, desktops) and Plasma 5 (clientActivated, desktop) APIs.
+const SERVICE = "%SERVICE%";
+const PATH = "/io/github/phucvinh57/ScreenInspector";
+const INTERFACE = "io.github.phucvinh57.ScreenInspector.KWin";
+
+function describe(window) {
+    if (!window) {
+        return null;
+    }
+    let desktopIndex = null;
+    let desktopName = null;
+    // Windows on all desktops get the current one
+    if (window.desktops !== undefined) {
+        const desktop = window.desktops[0] || workspace.currentDesktop;
+        if (desktop) {
+            desktopIndex = workspace.desktops.indexOf(desktop);
+            desktopName = desktop.name;
+        }
+    } else {
+        const desktop = window.desktop > 0 ? window.desktop : workspace.currentDesktop;
+        desktopIndex = desktop - 1;
+        desktopName = workspace.desktopName(desktop);
+    }
+    const geometry = window.frameGeometry || window.geometry;
+    return {
+        caption: window.caption,
+        resourceClass: String(window.resourceClass),
+        resourceName: String(window.resourceName),
+        pid: window.pid,
+        desktopIndex: desktopIndex,
+        desktopName: desktopName,
+        activity: window.activities.length > 0 ? window.activities[0] : workspace.currentActivity,
+        x: geometry.x,
+        y: geometry.y,
+        width: geometry.width,
+        height: geometry.height,
+        fullScreen: window.fullScreen,
+        minimized: window.minimized,
+        keepAbove: window.keepAbove,
+        demandsAttention: window.demandsAttention,
+    };
+}
+
+function report(event, window) {
+    callDBus(SERVICE, PATH, INTERFACE, "Report", JSON.stringify({
+        event: event,
+        window: describe(window),
+    }));
+}
+
+let active = null;
+
+function onCaptionChanged() {
+    report("caption", active);
+}
+
+function onActivated(window) {
+    if (active) {
+        active.captionChanged.disconnect(onCaptionChanged);
+    }
+    active = window;
+    if (active) {
+        active.captionChanged.connect(onCaptionChanged);
+    }
+    report("activated", active);
+}
+
+if (workspace.windowActivated !== undefined) {
+    workspace.windowActivated.connect(onActivated);
+    onActivated(workspace.activeWindow);
+} else {
+    workspace.clientActivated.connect(onActivated);
+    onActivated(workspace.activeClient);
+}
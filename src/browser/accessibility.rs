@@ -0,0 +1,431 @@
+// Read the address bar of the focused browser window through the AT-SPI accessibility tree.
+// Used when the session file of the browser can't be parsed (Epiphany, closed-source forks,
+// new session format versions).
+//
+// AT-SPI runs on its own bus, whose address is given by `org.a11y.Bus` on the session bus.
+// The registry lists every accessible application; we pick the one owned by the window PID,
+// find its active frame and read the text of the first entry that looks like an address bar.
+// The connection to the AT-SPI bus is kept for the next calls.
+#![cfg(target_os = "linux")]
+use {
+    crate::types::{BrowserTab, TabSource},
+    anyhow::{anyhow, Result},
+    log::debug,
+    std::collections::VecDeque,
+    std::sync::Mutex,
+    std::time::{Duration, Instant, SystemTime},
+    zbus::blocking::Connection,
+    zbus::zvariant::{OwnedObjectPath, OwnedValue},
+};
+
+const REGISTRY_NAME: &str = "org.a11y.atspi.Registry";
+const ROOT_PATH: &str = "/org/a11y/atspi/accessible/root";
+const ACCESSIBLE_IFACE: &str = "org.a11y.atspi.Accessible";
+const TEXT_IFACE: &str = "org.a11y.atspi.Text";
+
+const ROLE_FRAME: u32 = 23;
+const ROLE_TEXT: u32 = 61;
+const ROLE_ENTRY: u32 = 79;
+const ROLE_DOCUMENT_FRAME: u32 = 82;
+const ROLE_DOCUMENT_WEB: u32 = 95;
+const STATE_ACTIVE: u32 = 1;
+
+/// A hung application must not block the caller, every call is bounded by this.
+const CALL_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Bound of the whole walk, checked between calls.
+const WALK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Page content can hold thousands of nodes, the browser chrome only a few hundred.
+const MAX_VISITED_NODES: usize = 2000;
+
+/// Names browsers give to their location entry, lower-cased.
+const ADDRESS_BAR_NAMES: &[&str] = &["address", "location", "url", "omnibox"];
+
+type Node = (String, OwnedObjectPath);
+
+/// Connection shared by every call. Reset when a walk fails, e.g. after the bus restarted.
+static A11Y_BUS: Mutex<Option<Connection>> = Mutex::new(None);
+
+/// Active tab as shown by the address bar. Only the URL is known from the accessibility tree.
+pub fn get_address_bar_tab(pid: Option<u32>) -> Option<BrowserTab> {
+    let url = get_address_bar_text(pid)?;
+    let observed_at = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .ok()?
+        .as_secs();
+    Some(BrowserTab {
+        url,
+        title: None,
+        group: None,
+        pinned: None,
+        profile: None,
+        incognito: None,
+        navigated_at: None,
+        source: TabSource::Accessibility,
+        observed_at,
+    })
+}
+
+/// Text of the address bar in the active window of the application owned by `pid`.
+pub fn get_address_bar_text(pid: Option<u32>) -> Option<String> {
+    let mut bus = A11Y_BUS.lock().ok()?;
+    if bus.is_none() {
+        match connect_a11y_bus() {
+            Ok(conn) => *bus = Some(conn),
+            Err(e) => {
+                debug!("AT-SPI bus unavailable: {}", e);
+                return None;
+            }
+        }
+    }
+    let conn = bus.as_ref()?;
+    match find_address_bar_text(conn, pid, Instant::now() + WALK_TIMEOUT) {
+        Ok(text) => text,
+        Err(e) => {
+            debug!("AT-SPI walk failed: {}", e);
+            *bus = None;
+            None
+        }
+    }
+}
+
+fn connect_a11y_bus() -> Result<Connection> {
+    if let Ok(address) = std::env::var("AT_SPI_BUS_ADDRESS") {
+        return connect(&address);
+    }
+    let session = zbus::blocking::connection::Builder::session()?
+        .method_timeout(CALL_TIMEOUT)
+        .build()?;
+    let reply = session.call_method(
+        Some("org.a11y.Bus"),
+        "/org/a11y/bus",
+        Some("org.a11y.Bus"),
+        "GetAddress",
+        &(),
+    )?;
+    let address: String = reply.body().deserialize()?;
+    connect(&address)
+}
+
+fn connect(address: &str) -> Result<Connection> {
+    Ok(zbus::blocking::connection::Builder::address(address)?
+        .method_timeout(CALL_TIMEOUT)
+        .build()?)
+}
+
+/// Without a PID the application can't be told apart from other windows, so nothing is read.
+/// Fails once `deadline` has passed.
+fn find_address_bar_text(
+    conn: &Connection,
+    pid: Option<u32>,
+    deadline: Instant,
+) -> Result<Option<String>> {
+    let Some(pid) = pid else {
+        return Ok(None);
+    };
+    let root = (
+        REGISTRY_NAME.to_string(),
+        OwnedObjectPath::try_from(ROOT_PATH)?,
+    );
+    for app in get_children(conn, &root)? {
+        check_deadline(deadline)?;
+        if get_connection_pid(conn, &app.0).ok() != Some(pid) {
+            continue;
+        }
+        let Some(frame) = find_active_frame(conn, &app)? else {
+            continue;
+        };
+        debug!("Active frame {:?}", frame);
+        if let Some(text) = find_entry_text(conn, &frame, deadline)? {
+            return Ok(Some(text));
+        }
+    }
+    Ok(None)
+}
+
+fn find_active_frame(conn: &Connection, app: &Node) -> Result<Option<Node>> {
+    for child in get_children(conn, app)? {
+        if get_role(conn, &child)? == ROLE_FRAME && has_state(conn, &child, STATE_ACTIVE)? {
+            return Ok(Some(child));
+        }
+    }
+    Ok(None)
+}
+
+/// Breadth-first, since the address bar sits near the top of the frame.
+/// Named address bars win over the first URL-looking entry.
+fn find_entry_text(conn: &Connection, frame: &Node, deadline: Instant) -> Result<Option<String>> {
+    let mut queue: VecDeque<Node> = get_children(conn, frame)?.into();
+    let mut fallback: Option<String> = None;
+    let mut visited = 0;
+
+    while let Some(node) = queue.pop_front() {
+        visited += 1;
+        if visited > MAX_VISITED_NODES {
+            break;
+        }
+        check_deadline(deadline)?;
+        let role = get_role(conn, &node)?;
+        match role {
+            ROLE_ENTRY | ROLE_TEXT => {
+                let Ok(text) = get_text(conn, &node) else {
+                    continue;
+                };
+                let name = get_name(conn, &node).unwrap_or_default().to_lowercase();
+                if ADDRESS_BAR_NAMES.iter().any(|n| name.contains(n)) {
+                    return Ok(Some(text));
+                }
+                if fallback.is_none() && looks_like_url(&text) {
+                    fallback = Some(text);
+                }
+            }
+            ROLE_DOCUMENT_FRAME | ROLE_DOCUMENT_WEB => continue,
+            _ => queue.extend(get_children(conn, &node)?),
+        }
+    }
+    Ok(fallback)
+}
+
+fn check_deadline(deadline: Instant) -> Result<()> {
+    if Instant::now() >= deadline {
+        return Err(anyhow!("AT-SPI walk timed out"));
+    }
+    Ok(())
+}
+
+fn looks_like_url(text: &str) -> bool {
+    let text = text.trim();
+    !text.is_empty()
+        && !text.contains(char::is_whitespace)
+        && (text.contains("://") || text.starts_with("about:") || text.contains('.'))
+}
+
+fn get_children(conn: &Connection, node: &Node) -> Result<Vec<Node>> {
+    let reply = conn.call_method(
+        Some(node.0.as_str()),
+        node.1.as_str(),
+        Some(ACCESSIBLE_IFACE),
+        "GetChildren",
+        &(),
+    )?;
+    Ok(reply.body().deserialize()?)
+}
+
+fn get_role(conn: &Connection, node: &Node) -> Result<u32> {
+    let reply = conn.call_method(
+        Some(node.0.as_str()),
+        node.1.as_str(),
+        Some(ACCESSIBLE_IFACE),
+        "GetRole",
+        &(),
+    )?;
+    Ok(reply.body().deserialize()?)
+}
+
+/// States are a 64-bit set split into two u32 words.
+fn has_state(conn: &Connection, node: &Node, state: u32) -> Result<bool> {
+    let reply = conn.call_method(
+        Some(node.0.as_str()),
+        node.1.as_str(),
+        Some(ACCESSIBLE_IFACE),
+        "GetState",
+        &(),
+    )?;
+    let states: Vec<u32> = reply.body().deserialize()?;
+    let word = states.get((state / 32) as usize).copied().unwrap_or(0);
+    Ok(word & (1 << (state % 32)) != 0)
+}
+
+fn get_name(conn: &Connection, node: &Node) -> Result<String> {
+    let reply = conn.call_method(
+        Some(node.0.as_str()),
+        node.1.as_str(),
+        Some("org.freedesktop.DBus.Properties"),
+        "Get",
+        &(ACCESSIBLE_IFACE, "Name"),
+    )?;
+    let value: OwnedValue = reply.body().deserialize()?;
+    Ok(String::try_from(value)?)
+}
+
+fn get_text(conn: &Connection, node: &Node) -> Result<String> {
+    let reply = conn.call_method(
+        Some(node.0.as_str()),
+        node.1.as_str(),
+        Some(TEXT_IFACE),
+        "GetText",
+        &(0i32, -1i32),
+    )?;
+    Ok(reply.body().deserialize()?)
+}
+
+fn get_connection_pid(conn: &Connection, bus_name: &str) -> Result<u32> {
+    let reply = conn.call_method(
+        Some("org.freedesktop.DBus"),
+        "/org/freedesktop/DBus",
+        Some("org.freedesktop.DBus"),
+        "GetConnectionUnixProcessID",
+        &(bus_name,),
+    )?;
+    Ok(reply.body().deserialize()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::start_dbus_daemon;
+
+    struct MockAccessible {
+        role: u32,
+        state: Vec<u32>,
+        name: String,
+        children: Vec<Node>,
+    }
+
+    #[zbus::interface(name = "org.a11y.atspi.Accessible")]
+    impl MockAccessible {
+        fn get_children(&self) -> Vec<Node> {
+            self.children.clone()
+        }
+        fn get_role(&self) -> u32 {
+            self.role
+        }
+        fn get_state(&self) -> Vec<u32> {
+            self.state.clone()
+        }
+        #[zbus(property)]
+        fn name(&self) -> String {
+            self.name.clone()
+        }
+    }
+
+    struct MockText(String);
+
+    #[zbus::interface(name = "org.a11y.atspi.Text")]
+    impl MockText {
+        fn get_text(&self, _start: i32, _end: i32) -> String {
+            self.0.clone()
+        }
+    }
+
+    fn path(p: &str) -> OwnedObjectPath {
+        OwnedObjectPath::try_from(p).unwrap()
+    }
+
+    #[test]
+    fn test_read_address_bar_from_mock_registry() {
+        let Some((_daemon, address)) = start_dbus_daemon() else {
+            eprintln!("dbus-daemon not available, skipping");
+            return;
+        };
+        let server = zbus::blocking::connection::Builder::address(address.as_str())
+            .unwrap()
+            .name(REGISTRY_NAME)
+            .unwrap()
+            .build()
+            .unwrap();
+        let me = server.unique_name().unwrap().to_string();
+        let node = |p: &str| (me.clone(), path(p));
+        let accessible =
+            |role: u32, state: Vec<u32>, name: &str, children: Vec<Node>| MockAccessible {
+                role,
+                state,
+                name: name.to_string(),
+                children,
+            };
+
+        let objects = server.object_server();
+        objects
+            .at(
+                ROOT_PATH,
+                accessible(75, vec![0, 0], "root", vec![node("/app")]),
+            )
+            .unwrap();
+        objects
+            .at(
+                "/app",
+                accessible(
+                    75,
+                    vec![0, 0],
+                    "Web",
+                    vec![node("/inactive"), node("/frame")],
+                ),
+            )
+            .unwrap();
+        // Background window, its entry must not be picked
+        objects
+            .at(
+                "/inactive",
+                accessible(ROLE_FRAME, vec![0, 0], "Old", vec![node("/old")]),
+            )
+            .unwrap();
+        objects
+            .at(
+                "/old",
+                accessible(ROLE_ENTRY, vec![0, 0], "Address", vec![]),
+            )
+            .unwrap();
+        objects
+            .at("/old", MockText("https://old.test/".into()))
+            .unwrap();
+        objects
+            .at(
+                "/frame",
+                accessible(
+                    ROLE_FRAME,
+                    vec![1 << STATE_ACTIVE, 0],
+                    "New",
+                    vec![node("/toolbar")],
+                ),
+            )
+            .unwrap();
+        objects
+            .at(
+                "/toolbar",
+                accessible(63, vec![0, 0], "", vec![node("/search"), node("/location")]),
+            )
+            .unwrap();
+        objects
+            .at(
+                "/search",
+                accessible(ROLE_ENTRY, vec![0, 0], "Find", vec![]),
+            )
+            .unwrap();
+        objects.at("/search", MockText("rust docs".into())).unwrap();
+        objects
+            .at(
+                "/location",
+                accessible(ROLE_ENTRY, vec![0, 0], "Address and search bar", vec![]),
+            )
+            .unwrap();
+        objects
+            .at("/location", MockText("https://new.test/page".into()))
+            .unwrap();
+
+        let client = zbus::blocking::connection::Builder::address(address.as_str())
+            .unwrap()
+            .build()
+            .unwrap();
+        let deadline = Instant::now() + WALK_TIMEOUT;
+        let text = find_address_bar_text(&client, Some(std::process::id()), deadline).unwrap();
+        assert_eq!(text.as_deref(), Some("https://new.test/page"));
+
+        // Applications owned by another process are skipped
+        let text = find_address_bar_text(&client, Some(u32::MAX), deadline).unwrap();
+        assert!(text.is_none());
+        let text = find_address_bar_text(&client, None, deadline).unwrap();
+        assert!(text.is_none());
+
+        // Out of time
+        assert!(find_address_bar_text(&client, Some(std::process::id()), Instant::now()).is_err());
+    }
+
+    #[test]
+    fn test_looks_like_url() {
+        assert!(looks_like_url("example.com/path"));
+        assert!(looks_like_url("about:blank"));
+        assert!(!looks_like_url("rust docs"));
+        assert!(!looks_like_url(""));
+    }
+}
@@ -1,40 +1,47 @@
 use log::warn;
 
-use crate::types::Browser;
+use crate::types::{Browser, BrowserTab};
 use std::fs;
+use std::path::Path;
 
+#[cfg(target_os = "linux")]
+use super::accessibility;
+use super::cache::FileCache;
 use super::chromium;
 
+/// Latest session file of each session folder, rescanned only when the folder changes
+/// (the browser creates a new session file on startup and appends to it afterwards).
+static LATEST_SESSION_FILES: FileCache<Option<String>> = FileCache::new();
+/// Active tab parsed from each session file.
+static PARSED_SESSIONS: FileCache<Option<BrowserTab>> = FileCache::new();
+
 /// Get most recently modified session file from browser session folder
 fn get_current_active_session_file(browser: Browser) -> Option<String> {
-    let session_folder = get_session_folder_path(browser);
+    let session_folder = get_session_folder_path(browser)?;
+    LATEST_SESSION_FILES.get_or_compute(Path::new(&session_folder), || {
+        scan_latest_session_file(&session_folder)
+    })?
+}
 
-    let paths = fs::read_dir(session_folder?).ok()?;
+fn scan_latest_session_file(session_folder: &str) -> Option<String> {
+    let paths = fs::read_dir(session_folder).ok()?;
     let mut latest_file: Option<(String, std::time::SystemTime)> = None;
 
-    for path in paths {
-        if let Ok(entry) = path {
-            if !entry
-                .path()
-                .to_str()?
-                .split('/')
-                .last()?
-                .starts_with("Session_")
-            {
-                continue;
-            }
-            let metadata = fs::metadata(entry.path()).ok()?;
-            let modified = metadata.modified().ok()?;
-            if latest_file.is_none() || modified > latest_file.as_ref()?.1 {
-                latest_file = Some((entry.path().to_str()?.to_string(), modified));
-            }
+    for entry in paths.flatten() {
+        if !entry.file_name().to_str()?.starts_with("Session_") {
+            continue;
+        }
+        let metadata = fs::metadata(entry.path()).ok()?;
+        let modified = metadata.modified().ok()?;
+        if latest_file.is_none() || modified > latest_file.as_ref()?.1 {
+            latest_file = Some((entry.path().to_str()?.to_string(), modified));
         }
     }
 
     latest_file.map(|(path, _)| path)
 }
 
-fn get_session_folder_path(browser: Browser) -> Option<String> {
+pub(super) fn get_session_folder_path(browser: Browser) -> Option<String> {
     let binding = dirs::home_dir()?;
     let homedir = binding.to_str()?;
 
@@ -69,6 +76,23 @@ fn get_session_folder_path(browser: Browser) -> Option<String> {
             None
         };
     }
+    if browser == Browser::Chromium {
+        return if cfg!(target_os = "windows") {
+            Some(format!(
+                "{}\\AppData\\Local\\Chromium\\User Data\\Default\\Sessions",
+                homedir
+            ))
+        } else if cfg!(target_os = "linux") {
+            Some(format!("{}/.config/chromium/Default/Sessions", homedir))
+        } else if cfg!(target_os = "macos") {
+            Some(format!(
+                "{}/Library/Application Support/Chromium/Default/Sessions",
+                homedir
+            ))
+        } else {
+            None
+        };
+    }
     if browser == Browser::Opera {
         return if cfg!(target_os = "windows") {
             Some(format!(
@@ -93,7 +117,10 @@ fn get_session_folder_path(browser: Browser) -> Option<String> {
                 homedir
             ))
         } else if cfg!(target_os = "linux") {
-            Some(format!("{}/.config/BraveSoftware/Brave-Browser/Default/Session", homedir))
+            Some(format!(
+                "{}/.config/BraveSoftware/Brave-Browser/Default/Session",
+                homedir
+            ))
         } else if cfg!(target_os = "macos") {
             Some(format!(
                 "{}/Library/Application Support/BraveSoftware/Brave-Browser",
@@ -110,12 +137,16 @@ fn get_session_folder_path(browser: Browser) -> Option<String> {
         } else if cfg!(target_os = "linux") {
             let moz_folder = format!("{}/.mozilla/firefox", homedir);
             let content = fs::read_to_string(format!("{}/installs.ini", moz_folder)).ok()?;
-            let default_profile = content.lines()
+            let default_profile = content
+                .lines()
                 .find(|line| line.starts_with("Default="))?
                 .split('=')
                 .nth(1)?
                 .trim();
-            Some(format!("{}/{}/sessionstore-backups", moz_folder, default_profile))
+            Some(format!(
+                "{}/{}/sessionstore-backups",
+                moz_folder, default_profile
+            ))
         } else if cfg!(target_os = "macos") {
             Some(format!("{}/Library/Application Support/Firefox", homedir))
         } else {
@@ -127,27 +158,41 @@ fn get_session_folder_path(browser: Browser) -> Option<String> {
             Some(format!("{}/Library/Safari", homedir))
         } else {
             None
-        }
+        };
     }
     None
 }
 
-pub fn get_browser_active_tab_url(browser: Browser) -> Option<String> {
-    let session_file = get_current_active_session_file(browser)?;
-    chromium::get_current_active_url(session_file)
+/// `pid` is the browser process owning the focused window, used by the accessibility fallback.
+pub fn get_browser_active_tab(browser: Browser, pid: Option<u32>) -> Option<BrowserTab> {
+    let tab = get_current_active_session_file(browser).and_then(|session_file| {
+        PARSED_SESSIONS.get_or_compute(Path::new(&session_file), || {
+            chromium::get_current_active_tab(session_file.clone())
+        })?
+    });
+    if tab.is_some() {
+        return tab;
+    }
+
+    #[cfg(target_os = "linux")]
+    return accessibility::get_address_bar_tab(pid);
+    #[cfg(not(target_os = "linux"))]
+    {
+        let _ = pid;
+        None
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use env_logger;
     use log::debug;
 
     #[test]
-    fn test_get_browser_active_tab_url() {
+    fn test_get_browser_active_tab() {
         env_logger::init();
-        let url = get_browser_active_tab_url(Browser::Chrome);
-        assert!(url.is_none());
+        let tab = get_browser_active_tab(Browser::Chrome, None);
+        assert!(tab.is_none());
     }
 
     #[test]
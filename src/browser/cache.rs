@@ -0,0 +1,96 @@
+// Cache of values computed from a file or directory, invalidated when its size or mtime changes.
+// Session files are only appended to by the browser, so when nothing changed, a lookup costs
+// a single stat instead of a directory scan and a full reparse.
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::SystemTime,
+};
+
+/// Entries for session files that were rotated away are dropped past this size.
+const MAX_ENTRIES: usize = 64;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct FileStamp {
+    size: u64,
+    modified: SystemTime,
+}
+
+impl FileStamp {
+    fn of(path: &Path) -> Option<Self> {
+        let metadata = fs::metadata(path).ok()?;
+        Some(FileStamp {
+            size: metadata.len(),
+            modified: metadata.modified().ok()?,
+        })
+    }
+}
+
+pub(super) struct FileCache<T> {
+    entries: Mutex<BTreeMap<PathBuf, (FileStamp, T)>>,
+}
+
+impl<T: Clone> FileCache<T> {
+    pub const fn new() -> Self {
+        FileCache {
+            entries: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// Value cached for `path`, recomputed with `compute` when the path changed since.
+    /// Returns `None` if the path can't be stat'ed.
+    pub fn get_or_compute(&self, path: &Path, compute: impl FnOnce() -> T) -> Option<T> {
+        let stamp = FileStamp::of(path)?;
+        if let Ok(entries) = self.entries.lock() {
+            if let Some((cached_stamp, value)) = entries.get(path) {
+                if *cached_stamp == stamp {
+                    return Some(value.clone());
+                }
+            }
+        }
+
+        let value = compute();
+        if let Ok(mut entries) = self.entries.lock() {
+            if entries.len() >= MAX_ENTRIES {
+                entries.clear();
+            }
+            entries.insert(path.to_path_buf(), (stamp, value.clone()));
+        }
+        Some(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+    use std::cell::Cell;
+
+    #[test]
+    fn test_recompute_only_on_change() {
+        let dir = TempDir::new("cache");
+        dir.write("file", "a");
+        let path = dir.path().join("file");
+
+        let cache = FileCache::new();
+        let calls = Cell::new(0);
+        let compute = || {
+            calls.set(calls.get() + 1);
+            fs::read_to_string(&path).unwrap()
+        };
+
+        assert_eq!(cache.get_or_compute(&path, compute).as_deref(), Some("a"));
+        assert_eq!(cache.get_or_compute(&path, compute).as_deref(), Some("a"));
+        assert_eq!(calls.get(), 1);
+
+        // Size changes even when the mtime granularity hides the write
+        dir.write("file", "bb");
+        assert_eq!(cache.get_or_compute(&path, compute).as_deref(), Some("bb"));
+        assert_eq!(calls.get(), 2);
+
+        fs::remove_file(&path).unwrap();
+        assert!(cache.get_or_compute(&path, compute).is_none());
+    }
+}
@@ -1,8 +1,15 @@
 #![allow(dead_code)]
 // This lib is used to read SNSS file of browsers
+use crate::types::{BrowserTab, TabSource};
 use anyhow::Ok;
 use log::debug;
-use std::{collections::HashMap, fs::File, io::Read};
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::Read,
+    path::Path,
+    time::UNIX_EPOCH,
+};
 
 #[derive(Debug)]
 // TODO: Add more commands
@@ -12,6 +19,7 @@ enum SSNSCommand {
     UpdateTabNavigation = 6,
     SetSelectedNavigationIndex = 7,
     SetSelectedTabInIndex = 8,
+    SetPinnedState = 12,
     SetActiveWindow = 20,
     LastActiveTime = 21,
     SetTabGroup = 25,
@@ -27,6 +35,7 @@ impl SSNSCommand {
             6 => Some(SSNSCommand::UpdateTabNavigation),
             7 => Some(SSNSCommand::SetSelectedNavigationIndex),
             8 => Some(SSNSCommand::SetSelectedTabInIndex),
+            12 => Some(SSNSCommand::SetPinnedState),
             20 => Some(SSNSCommand::SetActiveWindow),
             21 => Some(SSNSCommand::LastActiveTime),
             25 => Some(SSNSCommand::SetTabGroup),
@@ -43,6 +52,8 @@ struct TabHistory {
     index: u32,
     url: String,
     title: String,
+    /// Unix timestamp of the navigation
+    timestamp: Option<u64>,
 }
 
 #[derive(Clone, Debug)]
@@ -61,6 +72,7 @@ struct Tab {
     deleted: bool,
     current_history_index: u32,
     group: Option<TabGroup>,
+    pinned: bool,
 }
 
 #[derive(Debug)]
@@ -72,11 +84,13 @@ struct Window {
 }
 
 const SNSS_HEADER: [u8; 4] = [0x53, 0x4E, 0x53, 0x53];
+/// Seconds between the Windows epoch (1601-01-01), used by Chrome's base::Time, and the Unix epoch
+const WINDOWS_TO_UNIX_EPOCH_SECS: u64 = 11_644_473_600;
 
 fn read_u8<R: Read>(mut f: R) -> Option<u8> {
     let mut buf = [0; 1];
-    f.read_exact(&mut buf).ok().unwrap();
-    return Some(buf[0]);
+    f.read_exact(&mut buf).ok()?;
+    Some(buf[0])
 }
 fn read_u16<R: Read>(mut f: R) -> Option<u16> {
     let mut buf = [0; 2];
@@ -103,6 +117,8 @@ fn read_string<R: Read>(mut f: R) -> Option<String> {
     }
     let mut buf = vec![0; rsize as usize];
     f.read_exact(&mut buf).ok()?;
+    // Drop the alignment padding
+    buf.truncate(size as usize);
     Some(String::from_utf8_lossy(&buf).to_string())
 }
 
@@ -116,6 +132,7 @@ fn read_string_16<R: Read>(mut f: R) -> Option<String> {
     let mut buf: Vec<u8> = vec![0; rsize as usize];
     f.read_exact(&mut buf).ok()?;
 
+    buf.truncate(size as usize * 2);
     let buf16 = buf
         .chunks(2)
         .map(|c| u16::from_le_bytes([c[0], c[1]]))
@@ -129,54 +146,45 @@ fn read_raw<R: Read>(mut f: R, size: usize) -> anyhow::Result<Vec<u8>> {
 }
 
 fn get_tab(tabs: &mut HashMap<u32, Tab>, tab_id: u32) -> &mut Tab {
-    if tabs.contains_key(&tab_id) {
-        tabs.get_mut(&tab_id).unwrap()
-    } else {
-        tabs.insert(
-            tab_id,
-            Tab {
-                index: 0,
-                histories: Vec::new(),
-                window_id: 0,
-                deleted: false,
-                current_history_index: 0,
-                group: None,
-            },
-        );
-        tabs.get_mut(&tab_id).unwrap()
-    }
+    tabs.entry(tab_id).or_insert_with(|| Tab {
+        index: 0,
+        histories: Vec::new(),
+        window_id: 0,
+        deleted: false,
+        current_history_index: 0,
+        group: None,
+        pinned: false,
+    })
 }
 fn get_window(windows: &mut HashMap<u32, Window>, window_id: u32) -> &mut Window {
-    if windows.contains_key(&window_id) {
-        windows.get_mut(&window_id).unwrap()
-    } else {
-        windows.insert(
-            window_id,
-            Window {
-                tabs: Vec::new(),
-                active_tab_index: 0,
-                active: false,
-                deleted: false,
-            },
-        );
-        windows.get_mut(&window_id).unwrap()
-    }
+    windows.entry(window_id).or_insert_with(|| Window {
+        tabs: Vec::new(),
+        active_tab_index: 0,
+        active: false,
+        deleted: false,
+    })
 }
 fn get_group(groups: &mut HashMap<String, TabGroup>, high: u64, low: u64) -> &mut TabGroup {
     let key = format!("{}|{}", high, low);
-    if groups.contains_key(&key) {
-        groups.get_mut(&key).unwrap()
-    } else {
-        groups.insert(
-            key.clone(),
-            TabGroup {
-                high,
-                low,
-                name: String::new(),
-            },
-        );
-        groups.get_mut(&key).unwrap()
-    }
+    groups.entry(key).or_insert_with(|| TabGroup {
+        high,
+        low,
+        name: String::new(),
+    })
+}
+
+/// Fields of a serialized navigation entry following the title, up to the timestamp.
+/// Returns the navigation time as a Unix timestamp.
+fn read_navigation_timestamp<R: Read>(mut f: R) -> Option<u64> {
+    read_string(&mut f)?; // Encoded page state
+    read_u32(&mut f)?; // Transition type
+    read_u32(&mut f)?; // Type mask
+    read_string(&mut f)?; // Referrer URL
+    read_u32(&mut f)?; // Referrer policy
+    read_string(&mut f)?; // Original request URL
+    read_u32(&mut f)?; // Is overriding user agent
+    let micros = read_u64(&mut f)?;
+    (micros / 1_000_000).checked_sub(WINDOWS_TO_UNIX_EPOCH_SECS)
 }
 
 // SNSS file format. No \n seperator
@@ -186,8 +194,15 @@ fn get_group(groups: &mut HashMap<String, TabGroup>, high: u64, low: u64) -> &mu
 // <int16(size)><int8(type id)><payload(size - 1 bytes)>
 // When user do an action, browser will append a "command" to SNSS file
 // From a list of commands, we can reconstruct the browser state, such as active tab, active window, etc ...
-pub fn get_current_active_url(path: String) -> Option<String> {
-    let mut f = File::open(path).ok()?;
+pub fn get_current_active_tab(path: String) -> Option<BrowserTab> {
+    let mut f = File::open(&path).ok()?;
+    let observed_at = f
+        .metadata()
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .unwrap_or_default()
+        .as_secs();
 
     // Read header
     let mut header_buf = [0; 4];
@@ -238,6 +253,7 @@ pub fn get_current_active_url(path: String) -> Option<String> {
                 let history_index = read_u32(&mut payload)?;
                 let url = read_string(&mut payload)?;
                 let title = read_string_16(&mut payload)?;
+                let timestamp = read_navigation_timestamp(&mut payload);
 
                 debug!("UpdateTabNavigation");
                 debug!(
@@ -252,12 +268,14 @@ pub fn get_current_active_url(path: String) -> Option<String> {
                     Some(h) => {
                         h.url = url;
                         h.title = title;
+                        h.timestamp = timestamp;
                     }
                     None => {
                         tab.histories.push(TabHistory {
                             index: history_index,
                             url,
                             title,
+                            timestamp,
                         });
                     }
                 }
@@ -272,6 +290,16 @@ pub fn get_current_active_url(path: String) -> Option<String> {
                 let window = get_window(&mut windows, window_id);
                 window.active_tab_index = active_tab_idx;
             }
+            SSNSCommand::SetPinnedState => {
+                debug!("SetPinnedState");
+                let tab_id = read_u32(&mut payload)?;
+                let pinned = read_u8(&mut payload)?;
+
+                debug!("Tab ID: {}, Pinned: {}", tab_id, pinned);
+
+                let tab = get_tab(&mut tabs, tab_id);
+                tab.pinned = pinned != 0;
+            }
             SSNSCommand::SetTabGroupMetadata2 => {
                 debug!("SetTabGroupMetadata2");
                 // Ignore size
@@ -364,12 +392,9 @@ pub fn get_current_active_url(path: String) -> Option<String> {
     for (window_id, window) in &windows {
         debug!("Window: {}, {:?}", window_id, window);
     }
-    let active_window = windows.iter_mut().find(|(_, w)| w.active && !w.deleted);
-    if active_window.is_none() {
-        return None;
-    }
+    let (active_window_id, active_window) =
+        windows.iter_mut().find(|(_, w)| w.active && !w.deleted)?;
     debug!("Active window {:?}", active_window);
-    let (active_window_id, active_window) = active_window.unwrap();
 
     for (_, tab) in tabs {
         if tab.deleted || tab.window_id != *active_window_id {
@@ -377,21 +402,118 @@ pub fn get_current_active_url(path: String) -> Option<String> {
         }
         active_window.tabs.push(tab);
     }
-    active_window.tabs.sort_by(|a, b| a.index.cmp(&b.index));
+    active_window.tabs.sort_by_key(|t| t.index);
 
-    let active_tab = active_window
+    let tab = active_window
         .tabs
         .iter()
-        .find(|t| t.index == active_window.active_tab_index);
-    if active_tab.is_none() {
-        return None;
-    }
-    let tab = active_tab.unwrap();
-    let current_active_url = tab
+        .find(|t| t.index == active_window.active_tab_index)?;
+    let history = tab
         .histories
         .iter()
-        .find(|h| h.index == tab.current_history_index)
-        .map(|h| h.clone().url);
+        .find(|h| h.index == tab.current_history_index)?;
+
+    // Session files live in <user data dir>/<profile>/Sessions
+    let profile = Path::new(&path)
+        .parent()
+        .and_then(Path::parent)
+        .and_then(Path::file_name)
+        .map(|p| p.to_string_lossy().to_string());
+
+    Some(BrowserTab {
+        url: history.url.clone(),
+        title: Some(history.title.clone()).filter(|t| !t.is_empty()),
+        group: tab
+            .group
+            .as_ref()
+            .map(|g| g.name.clone())
+            .filter(|n| !n.is_empty()),
+        pinned: Some(tab.pinned),
+        profile,
+        // Incognito windows are never written to session files
+        incognito: Some(false),
+        navigated_at: history.timestamp,
+        source: TabSource::SessionFile,
+        observed_at,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn pickle_string(buf: &mut Vec<u8>, s: &str) {
+        buf.extend((s.len() as u32).to_le_bytes());
+        buf.extend(s.as_bytes());
+        buf.resize(buf.len().next_multiple_of(4), 0);
+    }
+
+    fn pickle_string_16(buf: &mut Vec<u8>, s: &str) {
+        let chars: Vec<u16> = s.encode_utf16().collect();
+        buf.extend((chars.len() as u32).to_le_bytes());
+        buf.extend(chars.iter().flat_map(|c| c.to_le_bytes()));
+        buf.resize(buf.len().next_multiple_of(4), 0);
+    }
 
-    current_active_url
+    fn command(file: &mut Vec<u8>, id: u8, payload: &[u8]) {
+        file.extend(((payload.len() + 1) as u16).to_le_bytes());
+        file.push(id);
+        file.extend(payload);
+    }
+
+    fn u32s(values: &[u32]) -> Vec<u8> {
+        values.iter().flat_map(|v| v.to_le_bytes()).collect()
+    }
+
+    fn navigation(tab_id: u32, index: u32, url: &str, title: &str, unix_ts: u64) -> Vec<u8> {
+        let mut entry = u32s(&[tab_id, index]);
+        pickle_string(&mut entry, url);
+        pickle_string_16(&mut entry, title);
+        pickle_string(&mut entry, ""); // Page state
+        entry.extend(u32s(&[0, 0])); // Transition type, type mask
+        pickle_string(&mut entry, ""); // Referrer
+        entry.extend(u32s(&[0])); // Referrer policy
+        pickle_string(&mut entry, url); // Original request URL
+        entry.extend(u32s(&[0])); // Overriding user agent
+        entry.extend(((unix_ts + WINDOWS_TO_UNIX_EPOCH_SECS) * 1_000_000).to_le_bytes());
+
+        let mut payload = u32s(&[entry.len() as u32]);
+        payload.extend(entry);
+        payload
+    }
+
+    #[test]
+    fn test_get_current_active_tab() {
+        let mut file = SNSS_HEADER.to_vec();
+        file.extend(u32s(&[3])); // Version
+        command(&mut file, 0, &u32s(&[1, 10])); // Tab 10 in window 1
+        command(&mut file, 0, &u32s(&[1, 11])); // Tab 11 in window 1
+        command(&mut file, 2, &u32s(&[10, 0]));
+        command(&mut file, 2, &u32s(&[11, 1]));
+        command(&mut file, 6, &navigation(10, 0, "https://a.test/", "A", 1_700_000_000));
+        command(&mut file, 6, &navigation(11, 0, "https://b.test/", "B", 1_700_000_100));
+        command(&mut file, 7, &u32s(&[11, 0]));
+        command(&mut file, 12, &u32s(&[11, 1]));
+        command(&mut file, 8, &u32s(&[1, 1])); // Second tab selected
+        command(&mut file, 20, &u32s(&[1]));
+
+        let dir = std::env::temp_dir()
+            .join(format!("screen-inspector-snss-{}", std::process::id()))
+            .join("Profile 1")
+            .join("Sessions");
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("Session_1");
+        fs::write(&path, file).unwrap();
+
+        let tab = get_current_active_tab(path.to_str().unwrap().to_string()).unwrap();
+        assert_eq!(tab.url, "https://b.test/");
+        assert_eq!(tab.title.as_deref(), Some("B"));
+        assert_eq!(tab.pinned, Some(true));
+        assert_eq!(tab.profile.as_deref(), Some("Profile 1"));
+        assert_eq!(tab.navigated_at, Some(1_700_000_100));
+        assert_eq!(tab.source, TabSource::SessionFile);
+
+        fs::remove_dir_all(dir.parent().unwrap().parent().unwrap()).unwrap();
+    }
 }
@@ -0,0 +1,228 @@
+// Read the active tab from Chromium-based browsers started with `--remote-debugging-port`.
+// The DevTools HTTP endpoint `/json/list` lists every target, most recently focused first,
+// so it gives real-time URLs without waiting for the browser to flush its session file.
+use {
+    super::browser::get_session_folder_path,
+    crate::types::{Browser, BrowserTab, TabSource},
+    anyhow::{anyhow, Result},
+    log::debug,
+    serde::Deserialize,
+    std::fs,
+    std::io::{Read, Write},
+    std::net::{Ipv4Addr, SocketAddr, TcpStream},
+    std::path::{Path, PathBuf},
+    std::time::{Duration, SystemTime},
+};
+
+const PORT_FLAG: &str = "--remote-debugging-port=";
+const USER_DATA_DIR_FLAG: &str = "--user-data-dir=";
+const ACTIVE_PORT_FILE: &str = "DevToolsActivePort";
+const TIMEOUT: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Clone, Deserialize)]
+struct Target {
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    title: String,
+    #[serde(default)]
+    url: String,
+}
+
+/// Get the active tab through the DevTools endpoint of the browser process `pid`.
+/// `window_title` is used to pick the tab shown in the focused window when several are open.
+pub fn get_active_tab(
+    browser: &Browser,
+    pid: Option<u32>,
+    window_title: &str,
+) -> Option<BrowserTab> {
+    if *browser == Browser::Firefox || *browser == Browser::Safari {
+        return None;
+    }
+    let port = find_debugging_port(browser, pid)?;
+    debug!("DevTools port of {:?}: {}", browser, port);
+
+    let targets = match get_targets(SocketAddr::from((Ipv4Addr::LOCALHOST, port))) {
+        Ok(t) => t,
+        Err(e) => {
+            debug!("DevTools query failed: {}", e);
+            return None;
+        }
+    };
+    let target = pick_focused_target(&targets, window_title)?;
+    let observed_at = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .ok()?
+        .as_secs();
+    Some(BrowserTab {
+        url: target.url.clone(),
+        title: Some(target.title.clone()).filter(|t| !t.is_empty()),
+        group: None,
+        pinned: None,
+        profile: None,
+        incognito: None,
+        navigated_at: None,
+        source: TabSource::DevTools,
+        observed_at,
+    })
+}
+
+/// The port comes from the command line, or from `DevToolsActivePort` when the browser
+/// was started with port 0 (or by a launcher that hides its argv).
+fn find_debugging_port(browser: &Browser, pid: Option<u32>) -> Option<u16> {
+    let args = pid
+        .and_then(|pid| fs::read(format!("/proc/{}/cmdline", pid)).ok())
+        .map(|raw| parse_cmdline(&raw))
+        .unwrap_or_default();
+
+    if let Some(port) = port_from_args(&args) {
+        if port != 0 {
+            return Some(port);
+        }
+    }
+
+    let user_data_dir = args
+        .iter()
+        .find_map(|a| a.strip_prefix(USER_DATA_DIR_FLAG))
+        .map(PathBuf::from)
+        .or_else(|| default_user_data_dir(browser))?;
+    read_active_port_file(&user_data_dir)
+}
+
+fn parse_cmdline(raw: &[u8]) -> Vec<String> {
+    raw.split(|b| *b == 0)
+        .filter(|a| !a.is_empty())
+        .map(|a| String::from_utf8_lossy(a).to_string())
+        .collect()
+}
+
+fn port_from_args(args: &[String]) -> Option<u16> {
+    args.iter()
+        .find_map(|a| a.strip_prefix(PORT_FLAG))
+        .and_then(|p| p.parse().ok())
+}
+
+/// First line of `DevToolsActivePort` is the port, the second one the browser target path.
+fn read_active_port_file(user_data_dir: &Path) -> Option<u16> {
+    let content = fs::read_to_string(user_data_dir.join(ACTIVE_PORT_FILE)).ok()?;
+    content.lines().next()?.trim().parse().ok()
+}
+
+/// Session folders live in `<user data dir>/Default/Sessions`.
+fn default_user_data_dir(browser: &Browser) -> Option<PathBuf> {
+    let session_folder = PathBuf::from(get_session_folder_path(browser.clone())?);
+    let profile_dir = session_folder.parent()?;
+    if profile_dir.file_name()? != "Default" {
+        return None;
+    }
+    profile_dir.parent().map(Path::to_path_buf)
+}
+
+fn get_targets(addr: SocketAddr) -> Result<Vec<Target>> {
+    let body = http_get(addr, "/json/list")?;
+    Ok(serde_json::from_slice(&body)?)
+}
+
+fn http_get(addr: SocketAddr, path: &str) -> Result<Vec<u8>> {
+    let mut stream = TcpStream::connect_timeout(&addr, TIMEOUT)?;
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    let request = format!(
+        "GET {} HTTP/1.1\r\nHost: {}\r\nConnection: close\r\n\r\n",
+        path, addr
+    );
+    stream.write_all(request.as_bytes())?;
+
+    let mut response = Vec::new();
+    stream.read_to_end(&mut response)?;
+    let header_end = response
+        .windows(4)
+        .position(|w| w == b"\r\n\r\n")
+        .ok_or(anyhow!("Malformed HTTP response"))?;
+    let head = String::from_utf8_lossy(&response[..header_end]);
+    let status = head.split_whitespace().nth(1).unwrap_or("");
+    if status != "200" {
+        return Err(anyhow!("DevTools returned status {}", status));
+    }
+    Ok(response[header_end + 4..].to_vec())
+}
+
+/// Window titles look like "<tab title> - Google Chrome", so prefer the most recently
+/// focused page whose title prefixes the window title, then the most recently focused page.
+fn pick_focused_target<'a>(targets: &'a [Target], window_title: &str) -> Option<&'a Target> {
+    let pages: Vec<&Target> = targets
+        .iter()
+        .filter(|t| t.kind == "page" && !t.url.starts_with("devtools://"))
+        .collect();
+
+    pages
+        .iter()
+        .find(|t| !t.title.is_empty() && window_title.starts_with(&t.title))
+        .or_else(|| pages.first())
+        .copied()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+    use std::net::TcpListener;
+    use std::thread;
+
+    const TARGETS: &str = r#"[
+        {"id": "1", "type": "service_worker", "title": "sw", "url": "https://sw.test/sw.js"},
+        {"id": "2", "type": "page", "title": "Docs", "url": "https://docs.test/"},
+        {"id": "3", "type": "page", "title": "Mail", "url": "https://mail.test/"}
+    ]"#;
+
+    fn serve_once(body: &'static str) -> SocketAddr {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        let addr = listener.local_addr().unwrap();
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = Vec::new();
+            let mut buf = [0; 1024];
+            while !request.ends_with(b"\r\n\r\n") {
+                let n = stream.read(&mut buf).unwrap();
+                request.extend_from_slice(&buf[..n]);
+            }
+            assert!(request.starts_with(b"GET /json/list "));
+            write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+                body.len(),
+                body
+            )
+            .unwrap();
+        });
+        addr
+    }
+
+    #[test]
+    fn test_get_targets_from_mock_server() {
+        let targets = get_targets(serve_once(TARGETS)).unwrap();
+        assert_eq!(targets.len(), 3);
+
+        let focused = pick_focused_target(&targets, "Mail - Chromium").unwrap();
+        assert_eq!(focused.url, "https://mail.test/");
+        let fallback = pick_focused_target(&targets, "Something else").unwrap();
+        assert_eq!(fallback.url, "https://docs.test/");
+    }
+
+    #[test]
+    fn test_port_from_cmdline() {
+        let raw = b"/opt/chromium/chrome\0--remote-debugging-port=9222\0--user-data-dir=/tmp/qa\0";
+        let args = parse_cmdline(raw);
+        assert_eq!(args.len(), 3);
+        assert_eq!(port_from_args(&args), Some(9222));
+        assert_eq!(port_from_args(&args[..1]), None);
+    }
+
+    #[test]
+    fn test_read_active_port_file() {
+        let dir = TempDir::new("devtools");
+        dir.write(ACTIVE_PORT_FILE, "41235\n/devtools/browser/abc\n");
+        assert_eq!(read_active_port_file(dir.path()), Some(41235));
+        // Missing file
+        assert_eq!(read_active_port_file(&dir.path().join("none")), None);
+    }
+}
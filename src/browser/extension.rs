@@ -0,0 +1,672 @@
+// Localhost receiver for active-tab updates pushed by a companion browser extension.
+// This is an alternative to native messaging, which needs a manifest installed per browser.
+//
+// Protocol (HTTP/1.1 on 127.0.0.1 only):
+// POST /tab     body: one JSON tab update
+// POST /stream  body: newline-delimited JSON tab updates, applied as they arrive
+//               (the extension can keep this request open with a chunked body)
+// Every request must carry `Authorization: Bearer <token>`. CORS is only granted to
+// chrome-extension:// and moz-extension:// origins.
+// The extension re-sends the active tab at least every 30 s: updates older than a minute are
+// ignored, so the session files are read again once the extension stops reporting.
+// Request heads, chunk-size lines and stream lines are read with a size limit, connections
+// without traffic time out, and only a few connections are served at once.
+//
+// Tab update: {"browser": "chrome", "url": "https://...", "title": "...", "incognito": false,
+//              "group": "Work", "pinned": false, "profile": "Default", "navigatedAt": 1700000000}
+// Only `browser` and `url` are required.
+use {
+    crate::types::{Browser, BrowserTab, TabSource},
+    anyhow::{anyhow, Result},
+    log::{debug, warn},
+    serde::Deserialize,
+    std::collections::HashMap,
+    std::fs,
+    std::io::{self, BufRead, BufReader, Read, Write},
+    std::net::{Ipv4Addr, Shutdown, SocketAddr, TcpListener, TcpStream},
+    std::path::Path,
+    std::sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+    std::sync::{Arc, Mutex},
+    std::thread::{self, JoinHandle},
+    std::time::{Duration, SystemTime},
+};
+
+#[cfg(unix)]
+use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+
+const MAX_HEADER_SIZE: usize = 8 * 1024;
+const MAX_BODY_SIZE: usize = 64 * 1024;
+/// Chunk-size lines are a hex number with optional extensions.
+const MAX_CHUNK_LINE_SIZE: usize = 1024;
+const MAX_CONNECTIONS: usize = 16;
+const READ_TIMEOUT: Duration = Duration::from_secs(30);
+/// The extension only writes on tab changes, a stream may stay quiet for a while.
+const STREAM_READ_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+/// Updates older than this are ignored, the session files are read instead.
+const MAX_TAB_AGE: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TabUpdatePayload {
+    browser: String,
+    url: String,
+    #[serde(default)]
+    title: Option<String>,
+    #[serde(default)]
+    incognito: Option<bool>,
+    #[serde(default)]
+    group: Option<String>,
+    #[serde(default)]
+    pinned: Option<bool>,
+    #[serde(default)]
+    profile: Option<String>,
+    #[serde(default)]
+    navigated_at: Option<u64>,
+}
+
+/// Latest active tab reported by the extension of one browser.
+/// `tab.observed_at` is the time the update was received.
+#[derive(Debug, Clone)]
+pub struct TabUpdate {
+    pub browser: Browser,
+    pub tab: BrowserTab,
+}
+
+type TabStore = Arc<Mutex<HashMap<Browser, TabUpdate>>>;
+
+/// Store of the listener that is currently running, read by `get_current_window_information`.
+static ACTIVE_STORE: Mutex<Option<TabStore>> = Mutex::new(None);
+
+/// A running localhost receiver. Stops when dropped.
+pub struct TabListener {
+    addr: SocketAddr,
+    store: TabStore,
+    stopped: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl TabListener {
+    /// Address the listener is bound to. Useful when started with port 0.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Latest tab update received for the given browser.
+    pub fn latest(&self, browser: &Browser) -> Option<TabUpdate> {
+        self.store.lock().ok()?.get(browser).cloned()
+    }
+
+    pub fn stop(mut self) {
+        self.shutdown();
+    }
+
+    fn shutdown(&mut self) {
+        if self.stopped.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        // Wake up the blocking accept() so the thread can observe the flag
+        let _ = TcpStream::connect(self.addr);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+        if let Ok(mut active) = ACTIVE_STORE.lock() {
+            if active.as_ref().is_some_and(|s| Arc::ptr_eq(s, &self.store)) {
+                *active = None;
+            }
+        }
+    }
+}
+
+impl Drop for TabListener {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+/// Start listening on `127.0.0.1:<port>` for tab updates authenticated with `token`.
+/// The most recently started listener is the one used to fill `WindowInformation.url`.
+pub fn start_tab_listener(port: u16, token: &str) -> Result<TabListener> {
+    if token.is_empty() {
+        return Err(anyhow!("Tab listener token must not be empty"));
+    }
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, port))?;
+    let addr = listener.local_addr()?;
+    let store: TabStore = Arc::new(Mutex::new(HashMap::new()));
+    let stopped = Arc::new(AtomicBool::new(false));
+    let connections = Arc::new(AtomicUsize::new(0));
+
+    let handle = {
+        let store = store.clone();
+        let stopped = stopped.clone();
+        let token = token.to_string();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                if stopped.load(Ordering::SeqCst) {
+                    break;
+                }
+                let stream = match stream {
+                    Ok(s) => s,
+                    Err(e) => {
+                        warn!("Tab listener accept error: {}", e);
+                        continue;
+                    }
+                };
+                let Some(slot) = ConnectionSlot::take(&connections) else {
+                    warn!("Tab listener busy, dropping a connection");
+                    continue;
+                };
+                let store = store.clone();
+                let token = token.clone();
+                thread::spawn(move || {
+                    let _slot = slot;
+                    if let Err(e) = handle_connection(stream, &token, &store) {
+                        debug!("Tab listener connection error: {}", e);
+                    }
+                });
+            }
+        })
+    };
+
+    if let Ok(mut active) = ACTIVE_STORE.lock() {
+        *active = Some(store.clone());
+    }
+    debug!("Tab listener started on {}", addr);
+
+    Ok(TabListener {
+        addr,
+        store,
+        stopped,
+        handle: Some(handle),
+    })
+}
+
+/// One of the `MAX_CONNECTIONS` connections served at once, given back when dropped.
+struct ConnectionSlot(Arc<AtomicUsize>);
+
+impl ConnectionSlot {
+    fn take(connections: &Arc<AtomicUsize>) -> Option<ConnectionSlot> {
+        if connections.fetch_add(1, Ordering::SeqCst) >= MAX_CONNECTIONS {
+            connections.fetch_sub(1, Ordering::SeqCst);
+            return None;
+        }
+        Some(ConnectionSlot(connections.clone()))
+    }
+}
+
+impl Drop for ConnectionSlot {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Read the per-install token from the config dir, creating a random one on first use.
+/// The file is readable by the current user only.
+pub fn load_or_create_token() -> Result<String> {
+    let dir = dirs::config_dir()
+        .ok_or(anyhow!("No config directory"))?
+        .join("screen-inspector");
+    load_or_create_token_in(&dir)
+}
+
+fn load_or_create_token_in(dir: &Path) -> Result<String> {
+    let path = dir.join("extension-token");
+    if let Ok(token) = fs::read_to_string(&path) {
+        let token = token.trim();
+        if !token.is_empty() {
+            #[cfg(unix)]
+            fs::set_permissions(&path, fs::Permissions::from_mode(0o600))?;
+            return Ok(token.to_string());
+        }
+    }
+
+    let mut bytes = [0u8; 16];
+    getrandom::getrandom(&mut bytes).map_err(|e| anyhow!("No random source: {}", e))?;
+    let token = bytes
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect::<String>();
+    fs::create_dir_all(dir)?;
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    options.mode(0o600);
+    options.open(&path)?.write_all(token.as_bytes())?;
+    Ok(token)
+}
+
+/// Active tab of the given browser as last reported to the running listener, unless the
+/// extension stopped reporting it for `MAX_TAB_AGE`.
+pub fn get_active_tab(browser: &Browser) -> Option<BrowserTab> {
+    let store = ACTIVE_STORE.lock().ok()?.clone()?;
+    let update = store.lock().ok()?.get(browser).cloned()?;
+    let now = (now_nanos() / 1_000_000_000) as u64;
+    is_fresh(&update.tab, now).then_some(update.tab)
+}
+
+fn is_fresh(tab: &BrowserTab, now: u64) -> bool {
+    now.saturating_sub(tab.observed_at) <= MAX_TAB_AGE.as_secs()
+}
+
+fn handle_connection(stream: TcpStream, token: &str, store: &TabStore) -> Result<()> {
+    stream.set_read_timeout(Some(READ_TIMEOUT))?;
+    stream.set_write_timeout(Some(READ_TIMEOUT))?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut writer = stream;
+
+    let (method, path, headers) = match read_head(&mut reader) {
+        Ok(head) => head,
+        Err(e) => {
+            write_response(&mut writer, None, 400, "Bad Request")?;
+            return Err(e);
+        }
+    };
+
+    // Only extension pages may read the responses, web pages get no CORS headers
+    let origin = headers
+        .get("origin")
+        .map(String::as_str)
+        .filter(|origin| is_extension_origin(origin));
+    if method == "OPTIONS" {
+        return match origin {
+            Some(_) => write_response(&mut writer, origin, 204, "No Content"),
+            None => write_response(&mut writer, None, 403, "Forbidden"),
+        };
+    }
+
+    let authorized = headers
+        .get("authorization")
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .is_some_and(|t| constant_time_eq(t.trim().as_bytes(), token.as_bytes()));
+    let chunked = headers
+        .get("transfer-encoding")
+        .is_some_and(|v| v.eq_ignore_ascii_case("chunked"));
+    let content_length = headers
+        .get("content-length")
+        .and_then(|v| v.parse::<usize>().ok());
+
+    if !authorized {
+        // Drain a small body so closing the socket doesn't reset the connection
+        if !chunked && content_length.is_some_and(|len| len <= MAX_BODY_SIZE) {
+            let mut body = vec![0; content_length.unwrap_or(0)];
+            let _ = reader.read_exact(&mut body);
+        }
+        return write_response(&mut writer, origin, 401, "Unauthorized");
+    }
+
+    match (method.as_str(), path.as_str()) {
+        ("POST", "/tab") => {
+            let body = if chunked {
+                let mut body = Vec::new();
+                ChunkedReader::new(&mut reader)
+                    .take(MAX_BODY_SIZE as u64)
+                    .read_to_end(&mut body)?;
+                body
+            } else {
+                let len = content_length.unwrap_or(0);
+                if len > MAX_BODY_SIZE {
+                    return write_response(&mut writer, origin, 413, "Payload Too Large");
+                }
+                let mut body = vec![0; len];
+                reader.read_exact(&mut body)?;
+                body
+            };
+            match apply_update(&body, store) {
+                Ok(_) => write_response(&mut writer, origin, 204, "No Content"),
+                Err(e) => {
+                    debug!("Invalid tab update: {}", e);
+                    write_response(&mut writer, origin, 400, "Bad Request")
+                }
+            }
+        }
+        ("POST", "/stream") => {
+            let body: Box<dyn Read> = if chunked {
+                Box::new(ChunkedReader::new(reader))
+            } else {
+                Box::new(reader.take(content_length.unwrap_or(0) as u64))
+            };
+            writer.set_read_timeout(Some(STREAM_READ_TIMEOUT))?;
+            let mut body = BufReader::new(body);
+            loop {
+                let line = read_line_limited(&mut body, MAX_BODY_SIZE)?;
+                if line.is_empty() {
+                    break;
+                }
+                if line.trim_ascii().is_empty() {
+                    continue;
+                }
+                if let Err(e) = apply_update(&line, store) {
+                    debug!("Invalid tab update in stream: {}", e);
+                }
+            }
+            write_response(&mut writer, origin, 204, "No Content")
+        }
+        _ => write_response(&mut writer, origin, 404, "Not Found"),
+    }
+}
+
+fn apply_update(body: &[u8], store: &TabStore) -> Result<()> {
+    let payload: TabUpdatePayload = serde_json::from_slice(body)?;
+    let browser = browser_from_name(&payload.browser)
+        .ok_or(anyhow!("Unknown browser: {}", payload.browser))?;
+    debug!("Tab update from {:?}: {}", browser, payload.url);
+
+    let update = TabUpdate {
+        browser: browser.clone(),
+        tab: BrowserTab {
+            url: payload.url,
+            title: payload.title,
+            group: payload.group,
+            pinned: payload.pinned,
+            profile: payload.profile,
+            incognito: payload.incognito,
+            navigated_at: payload.navigated_at,
+            source: TabSource::Extension,
+            observed_at: (now_nanos() / 1_000_000_000) as u64,
+        },
+    };
+    store
+        .lock()
+        .map_err(|_| anyhow!("Tab store poisoned"))?
+        .insert(browser, update);
+    Ok(())
+}
+
+fn browser_from_name(name: &str) -> Option<Browser> {
+    match name.to_lowercase().as_str() {
+        "chrome" => Some(Browser::Chrome),
+        "chromium" => Some(Browser::Chromium),
+        "opera" => Some(Browser::Opera),
+        "brave" => Some(Browser::Brave),
+        "edge" | "msedge" => Some(Browser::Edge),
+        "firefox" => Some(Browser::Firefox),
+        "safari" => Some(Browser::Safari),
+        "other" => Some(Browser::Other),
+        _ => None,
+    }
+}
+
+type RequestHead = (String, String, HashMap<String, String>);
+
+fn read_head<R: BufRead>(reader: &mut R) -> Result<RequestHead> {
+    // Reading stops at the limit, an oversized head then looks incomplete
+    let mut reader = reader.take(MAX_HEADER_SIZE as u64);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().ok_or(anyhow!("Missing method"))?.to_string();
+    let path = parts.next().ok_or(anyhow!("Missing path"))?.to_string();
+
+    let mut headers = HashMap::new();
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 {
+            return Err(anyhow!("Incomplete or oversized request head"));
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            headers.insert(name.trim().to_lowercase(), value.trim().to_string());
+        }
+    }
+    Ok((method, path, headers))
+}
+
+fn is_extension_origin(origin: &str) -> bool {
+    ["chrome-extension://", "moz-extension://"]
+        .iter()
+        .any(|scheme| origin.starts_with(scheme))
+}
+
+/// Empty response, with CORS headers for `origin` when it is an extension origin.
+fn write_response(
+    w: &mut TcpStream,
+    origin: Option<&str>,
+    status: u16,
+    reason: &str,
+) -> Result<()> {
+    write!(w, "HTTP/1.1 {} {}\r\n", status, reason)?;
+    if let Some(origin) = origin {
+        write!(
+            w,
+            "Access-Control-Allow-Origin: {}\r\n\
+             Access-Control-Allow-Headers: Authorization, Content-Type\r\n\
+             Access-Control-Allow-Methods: POST, OPTIONS\r\n\
+             Vary: Origin\r\n",
+            origin
+        )?;
+    }
+    write!(w, "Content-Length: 0\r\nConnection: close\r\n\r\n")?;
+    w.flush()?;
+    let _ = w.shutdown(Shutdown::Write);
+    Ok(())
+}
+
+/// Decodes a `Transfer-Encoding: chunked` body.
+struct ChunkedReader<R: BufRead> {
+    inner: R,
+    remaining: usize,
+    done: bool,
+}
+
+impl<R: BufRead> ChunkedReader<R> {
+    fn new(inner: R) -> Self {
+        ChunkedReader {
+            inner,
+            remaining: 0,
+            done: false,
+        }
+    }
+}
+
+impl<R: BufRead> Read for ChunkedReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if self.done || buf.is_empty() {
+            return Ok(0);
+        }
+        if self.remaining == 0 {
+            let size_line = read_line_limited(&mut self.inner, MAX_CHUNK_LINE_SIZE)?;
+            let size_line = String::from_utf8_lossy(&size_line);
+            let size = size_line.trim().split(';').next().unwrap_or("");
+            let size = usize::from_str_radix(size, 16).map_err(|_| {
+                std::io::Error::new(std::io::ErrorKind::InvalidData, "Invalid chunk size")
+            })?;
+            if size == 0 {
+                self.done = true;
+                return Ok(0);
+            }
+            self.remaining = size;
+        }
+
+        let max = buf.len().min(self.remaining);
+        let n = self.inner.read(&mut buf[..max])?;
+        if n == 0 {
+            return Err(std::io::ErrorKind::UnexpectedEof.into());
+        }
+        self.remaining -= n;
+        if self.remaining == 0 {
+            // Consume the CRLF after the chunk data
+            read_line_limited(&mut self.inner, MAX_CHUNK_LINE_SIZE)?;
+        }
+        Ok(n)
+    }
+}
+
+/// Line of at most `max` bytes, newline included, empty at the end of the input. Longer lines
+/// are an error, so a client can't make us buffer without bound.
+fn read_line_limited<R: BufRead>(reader: &mut R, max: usize) -> io::Result<Vec<u8>> {
+    let mut line = Vec::new();
+    reader.take(max as u64).read_until(b'\n', &mut line)?;
+    if line.len() == max && !line.ends_with(b"\n") {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "Line too long"));
+    }
+    Ok(line)
+}
+
+/// Compare without returning at the first difference, so the response time doesn't tell
+/// how much of a guessed token is right.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+fn now_nanos() -> u128 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn send(addr: SocketAddr, request: &str) -> String {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        stream.write_all(request.as_bytes()).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    }
+
+    fn post(addr: SocketAddr, path: &str, token: &str, body: &str) -> String {
+        send(
+            addr,
+            &format!(
+                "POST {} HTTP/1.1\r\nHost: localhost\r\nAuthorization: Bearer {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+                path,
+                token,
+                body.len(),
+                body
+            ),
+        )
+    }
+
+    #[test]
+    fn test_post_tab_update() {
+        let listener = start_tab_listener(0, "secret").unwrap();
+        let response = post(
+            listener.local_addr(),
+            "/tab",
+            "secret",
+            r#"{"browser":"firefox","url":"https://example.com/","title":"Example"}"#,
+        );
+        assert!(response.starts_with("HTTP/1.1 204"));
+
+        let update = listener.latest(&Browser::Firefox).unwrap();
+        assert_eq!(update.tab.url, "https://example.com/");
+        assert_eq!(update.tab.title.as_deref(), Some("Example"));
+        assert_eq!(update.tab.source, TabSource::Extension);
+        assert!(update.tab.incognito.is_none());
+    }
+
+    #[test]
+    fn test_rejects_wrong_token() {
+        let listener = start_tab_listener(0, "secret").unwrap();
+        let response = post(
+            listener.local_addr(),
+            "/tab",
+            "wrong",
+            r#"{"browser":"chrome","url":"https://example.com/"}"#,
+        );
+        assert!(response.starts_with("HTTP/1.1 401"));
+        assert!(listener.latest(&Browser::Chrome).is_none());
+    }
+
+    #[test]
+    fn test_rejects_invalid_payload() {
+        let listener = start_tab_listener(0, "secret").unwrap();
+        let response = post(listener.local_addr(), "/tab", "secret", r#"{"url":1}"#);
+        assert!(response.starts_with("HTTP/1.1 400"));
+    }
+
+    #[test]
+    fn test_cors_only_for_extensions() {
+        let listener = start_tab_listener(0, "secret").unwrap();
+        let preflight = |origin: &str| {
+            send(
+                listener.local_addr(),
+                &format!("OPTIONS /tab HTTP/1.1\r\nOrigin: {}\r\n\r\n", origin),
+            )
+        };
+        let response = preflight("chrome-extension://abcdefghijklmnop");
+        assert!(response.starts_with("HTTP/1.1 204"));
+        assert!(
+            response.contains("Access-Control-Allow-Origin: chrome-extension://abcdefghijklmnop")
+        );
+        let response = preflight("https://evil.example");
+        assert!(response.starts_with("HTTP/1.1 403"));
+        assert!(!response.contains("Access-Control-Allow-Origin"));
+    }
+
+    #[test]
+    fn test_stale_tab() {
+        let tab = BrowserTab {
+            url: "https://example.com/".into(),
+            title: None,
+            group: None,
+            pinned: None,
+            profile: None,
+            incognito: None,
+            navigated_at: None,
+            source: TabSource::Extension,
+            observed_at: 1_700_000_000,
+        };
+        assert!(is_fresh(&tab, 1_700_000_030));
+        assert!(!is_fresh(&tab, 1_700_000_000 + MAX_TAB_AGE.as_secs() + 1));
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_load_or_create_token() {
+        use crate::test_util::TempDir;
+
+        let dir = TempDir::new("token");
+        let token = load_or_create_token_in(dir.path()).unwrap();
+        assert_eq!(token.len(), 32);
+        let path = dir.path().join("extension-token");
+        let mode = || fs::metadata(&path).unwrap().permissions().mode() & 0o777;
+        assert_eq!(mode(), 0o600);
+        assert_eq!(load_or_create_token_in(dir.path()).unwrap(), token);
+
+        // A readable token file is reused and made private
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o644)).unwrap();
+        assert_eq!(load_or_create_token_in(dir.path()).unwrap(), token);
+        assert_eq!(mode(), 0o600);
+    }
+
+    #[test]
+    fn test_bounded_reads() {
+        let long = format!("GET /{} HTTP/1.1\r\n\r\n", "a".repeat(MAX_HEADER_SIZE));
+        assert!(read_head(&mut io::Cursor::new(long)).is_err());
+        let head = read_head(&mut io::Cursor::new("POST /tab HTTP/1.1\r\nA: b\r\n\r\n")).unwrap();
+        assert_eq!((head.0.as_str(), head.1.as_str()), ("POST", "/tab"));
+
+        let mut lines = io::Cursor::new("short\nlonger line\n");
+        assert_eq!(read_line_limited(&mut lines, 8).unwrap(), b"short\n");
+        assert!(read_line_limited(&mut lines, 8).is_err());
+        // Endless chunk-size line
+        let mut chunked = ChunkedReader::new(io::Cursor::new("f".repeat(MAX_CHUNK_LINE_SIZE * 2)));
+        assert!(chunked.read_to_end(&mut Vec::new()).is_err());
+    }
+
+    #[test]
+    fn test_chunked_stream() {
+        let listener = start_tab_listener(0, "secret").unwrap();
+        let first = "{\"browser\":\"brave\",\"url\":\"https://a.test/\"}\n";
+        let second = "{\"browser\":\"brave\",\"url\":\"https://b.test/\"}\n";
+        let request = format!(
+            "POST /stream HTTP/1.1\r\nAuthorization: Bearer secret\r\nTransfer-Encoding: chunked\r\n\r\n{:x}\r\n{}\r\n{:x}\r\n{}\r\n0\r\n\r\n",
+            first.len(),
+            first,
+            second.len(),
+            second
+        );
+        let response = send(listener.local_addr(), &request);
+        assert!(response.starts_with("HTTP/1.1 204"));
+        assert_eq!(
+            listener.latest(&Browser::Brave).unwrap().tab.url,
+            "https://b.test/"
+        );
+    }
+}
@@ -0,0 +1,239 @@
+// Decide whether a window belongs to a browser, and which one.
+// Several signals are checked with an explicit precedence, each one matched against
+// exact known names instead of substrings, so `chrome-remote-desktop`, PWA windows
+// (`crx_*` WM_CLASS instances) and Electron apps are not mistaken for browsers.
+use {crate::types::Browser, std::fs, std::path::Path};
+
+/// Signal that decided the identification, from most to least trusted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IdentificationSignal {
+    /// Desktop file the process was launched from (`GIO_LAUNCHED_DESKTOP_FILE`).
+    DesktopId,
+    /// First WM_CLASS string (res_name). On Windows and macOS there is only one class entry,
+    /// which is reported as `WmClass`.
+    WmClassInstance,
+    /// Second WM_CLASS string (res_class).
+    WmClass,
+    /// Basename of the executable path.
+    ExecName,
+    /// Process name from `/proc/<pid>/comm`.
+    ProcessName,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BrowserIdentification {
+    pub browser: Browser,
+    pub signal: IdentificationSignal,
+    /// The normalized name that matched.
+    pub matched: String,
+}
+
+/// Signals collected for one window. Any of them may be missing.
+#[derive(Debug, Default)]
+pub struct IdentificationInput<'a> {
+    pub desktop_id: Option<&'a str>,
+    pub wm_class_instance: Option<&'a str>,
+    pub wm_class: Option<&'a str>,
+    pub exec_path: Option<&'a str>,
+    pub process_name: Option<&'a str>,
+}
+
+const KNOWN_BROWSERS: &[(&str, Browser)] = &[
+    ("google-chrome", Browser::Chrome),
+    ("google-chrome-stable", Browser::Chrome),
+    ("google-chrome-beta", Browser::Chrome),
+    ("google-chrome-unstable", Browser::Chrome),
+    ("com.google.chrome", Browser::Chrome),
+    ("google chrome", Browser::Chrome),
+    ("chrome", Browser::Chrome),
+    ("chromium", Browser::Chromium),
+    ("chromium-browser", Browser::Chromium),
+    ("org.chromium.chromium", Browser::Chromium),
+    ("brave", Browser::Brave),
+    ("brave-browser", Browser::Brave),
+    ("brave-browser-stable", Browser::Brave),
+    ("com.brave.browser", Browser::Brave),
+    ("brave browser", Browser::Brave),
+    ("opera", Browser::Opera),
+    ("com.opera.opera", Browser::Opera),
+    ("microsoft-edge", Browser::Edge),
+    ("microsoft-edge-stable", Browser::Edge),
+    ("microsoft-edge-beta", Browser::Edge),
+    ("microsoft-edge-dev", Browser::Edge),
+    ("com.microsoft.edge", Browser::Edge),
+    ("microsoft edge", Browser::Edge),
+    ("msedge", Browser::Edge),
+    ("firefox", Browser::Firefox),
+    ("firefox-esr", Browser::Firefox),
+    ("firefox-bin", Browser::Firefox),
+    ("org.mozilla.firefox", Browser::Firefox),
+    ("safari", Browser::Safari),
+    ("com.apple.safari", Browser::Safari),
+    ("vivaldi", Browser::Other),
+    ("vivaldi-stable", Browser::Other),
+    ("vivaldi-bin", Browser::Other),
+    ("epiphany", Browser::Other),
+    ("org.gnome.epiphany", Browser::Other),
+    ("librewolf", Browser::Other),
+    ("io.gitlab.librewolf-community", Browser::Other),
+    ("waterfox", Browser::Other),
+    ("floorp", Browser::Other),
+    ("falkon", Browser::Other),
+    ("org.kde.falkon", Browser::Other),
+    ("qutebrowser", Browser::Other),
+    ("org.qutebrowser.qutebrowser", Browser::Other),
+    ("midori", Browser::Other),
+    ("yandex-browser", Browser::Other),
+];
+
+/// Identify the browser from the collected signals, checking them in the order of
+/// `IdentificationSignal`. Returns `None` for non-browser windows, including browser-hosted
+/// app windows (Chrome/Edge PWAs), since their URL is not a browsing tab.
+pub fn identify(input: &IdentificationInput) -> Option<BrowserIdentification> {
+    if input
+        .wm_class_instance
+        .is_some_and(|i| i.to_lowercase().starts_with("crx_"))
+    {
+        return None;
+    }
+
+    let exec_name = input.exec_path.and_then(basename);
+    let desktop_id = input.desktop_id.and_then(basename);
+    let signals = [
+        (IdentificationSignal::DesktopId, desktop_id),
+        (
+            IdentificationSignal::WmClassInstance,
+            input.wm_class_instance,
+        ),
+        (IdentificationSignal::WmClass, input.wm_class),
+        (IdentificationSignal::ExecName, exec_name),
+        (IdentificationSignal::ProcessName, input.process_name),
+    ];
+
+    signals.iter().find_map(|(signal, value)| {
+        let name = normalize(value.as_ref()?);
+        let (_, browser) = KNOWN_BROWSERS.iter().find(|(known, _)| *known == name)?;
+        Some(BrowserIdentification {
+            browser: browser.clone(),
+            signal: *signal,
+            matched: name,
+        })
+    })
+}
+
+/// Lower-case and strip the suffixes that differ between platforms and packaging.
+fn normalize(name: &str) -> String {
+    let name = name.trim().to_lowercase();
+    let name = name.strip_suffix(".desktop").unwrap_or(&name);
+    let name = name.strip_suffix(".exe").unwrap_or(name);
+    let name = name.strip_suffix(".app").unwrap_or(name);
+    name.to_string()
+}
+
+fn basename(path: &str) -> Option<&str> {
+    path.rsplit(['/', '\\']).next().filter(|s| !s.is_empty())
+}
+
+/// Desktop file id the process was launched from. GIO exports it to the child environment,
+/// so only trust it when `GIO_LAUNCHED_DESKTOP_FILE_PID` is the process itself.
+pub fn get_desktop_id(pid: u32) -> Option<String> {
+    let environ = fs::read(format!("/proc/{}/environ", pid)).ok()?;
+    let mut desktop_file = None;
+    let mut launched_pid = None;
+    for var in environ.split(|b| *b == 0) {
+        let var = String::from_utf8_lossy(var);
+        if let Some(v) = var.strip_prefix("GIO_LAUNCHED_DESKTOP_FILE=") {
+            desktop_file = Some(v.to_string());
+        } else if let Some(v) = var.strip_prefix("GIO_LAUNCHED_DESKTOP_FILE_PID=") {
+            launched_pid = v.parse::<u32>().ok();
+        }
+    }
+    if launched_pid != Some(pid) {
+        return None;
+    }
+    let desktop_file = desktop_file?;
+    let id = Path::new(&desktop_file).file_name()?.to_str()?;
+    Some(id.to_string())
+}
+
+pub fn get_process_name(pid: u32) -> Option<String> {
+    let comm = fs::read_to_string(format!("/proc/{}/comm", pid)).ok()?;
+    Some(comm.trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn x11<'a>(instance: &'a str, class: &'a str, exec_path: &'a str) -> IdentificationInput<'a> {
+        IdentificationInput {
+            wm_class_instance: Some(instance),
+            wm_class: Some(class),
+            exec_path: Some(exec_path),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_identify_by_wm_class_instance() {
+        let id = identify(&x11(
+            "google-chrome",
+            "Google-chrome",
+            "/opt/google/chrome/chrome",
+        ))
+        .unwrap();
+        assert_eq!(id.browser, Browser::Chrome);
+        assert_eq!(id.signal, IdentificationSignal::WmClassInstance);
+
+        let id = identify(&x11("Navigator", "firefox", "/usr/lib/firefox/firefox")).unwrap();
+        assert_eq!(id.browser, Browser::Firefox);
+        assert_eq!(id.signal, IdentificationSignal::WmClass);
+    }
+
+    #[test]
+    fn test_rejects_lookalikes() {
+        // Chrome PWA window
+        assert!(identify(&x11(
+            "crx_abcdef",
+            "Google-chrome",
+            "/opt/google/chrome/chrome"
+        ))
+        .is_none());
+        // Chrome Remote Desktop and Electron apps
+        assert!(identify(&x11(
+            "chrome-remote-desktop-client",
+            "Chrome-remote-desktop-client",
+            "/opt/google/chrome-remote-desktop/chrome-remote-desktop-host"
+        ))
+        .is_none());
+        assert!(identify(&x11("code", "Code", "/usr/share/code/code")).is_none());
+    }
+
+    #[test]
+    fn test_precedence() {
+        let input = IdentificationInput {
+            desktop_id: Some("/usr/share/applications/vivaldi-stable.desktop"),
+            wm_class_instance: Some("vivaldi-stable"),
+            exec_path: Some("/opt/vivaldi/vivaldi-bin"),
+            ..Default::default()
+        };
+        let id = identify(&input).unwrap();
+        assert_eq!(id.browser, Browser::Other);
+        assert_eq!(id.signal, IdentificationSignal::DesktopId);
+        assert_eq!(id.matched, "vivaldi-stable");
+
+        let input = IdentificationInput {
+            wm_class: Some("Safari"),
+            ..Default::default()
+        };
+        assert_eq!(identify(&input).unwrap().browser, Browser::Safari);
+
+        let input = IdentificationInput {
+            exec_path: Some("C:\\Program Files\\Microsoft\\Edge\\Application\\msedge.exe"),
+            ..Default::default()
+        };
+        let id = identify(&input).unwrap();
+        assert_eq!(id.browser, Browser::Edge);
+        assert_eq!(id.signal, IdentificationSignal::ExecName);
+    }
+}
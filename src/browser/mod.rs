@@ -1,4 +1,19 @@
+mod accessibility;
+#[allow(clippy::module_inception)]
 mod browser;
+mod cache;
 mod chromium;
+mod devtools;
+mod extension;
+mod identify;
 
-pub use browser::get_browser_active_tab_url;
\ No newline at end of file
+pub use browser::get_browser_active_tab;
+pub use devtools::get_active_tab as get_devtools_active_tab;
+pub use extension::{
+    get_active_tab as get_extension_active_tab, load_or_create_token, start_tab_listener,
+    TabListener, TabUpdate,
+};
+pub use identify::{
+    get_desktop_id, get_process_name, identify, BrowserIdentification, IdentificationInput,
+    IdentificationSignal,
+};
@@ -0,0 +1,73 @@
+use std::fmt;
+
+/// Why the focused window couldn't be read.
+#[derive(Debug)]
+pub enum InspectError {
+    /// No display server to connect to, e.g. in a TTY or a headless session.
+    NoDisplay,
+    /// No backend works in this session. Holds the reason given by the most specific one.
+    BackendUnavailable(String),
+    /// The display server or the desktop shell refused the request.
+    PermissionDenied(String),
+    /// The focused window went away while it was being read.
+    NoFocusedWindow,
+    /// The selected backend works but can't do this operation, e.g. list windows.
+    Unsupported(String),
+}
+
+impl fmt::Display for InspectError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            InspectError::NoDisplay => write!(f, "No display available"),
+            InspectError::BackendUnavailable(reason) => {
+                write!(f, "No window backend available: {}", reason)
+            }
+            InspectError::PermissionDenied(reason) => write!(f, "Permission denied: {}", reason),
+            InspectError::NoFocusedWindow => write!(f, "The focused window was closed"),
+            InspectError::Unsupported(reason) => write!(f, "Unsupported: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for InspectError {}
+
+impl From<anyhow::Error> for InspectError {
+    /// Backends report an `InspectError` when they know the cause, any other error means
+    /// the backend can't run.
+    fn from(error: anyhow::Error) -> Self {
+        let error = match error.downcast::<InspectError>() {
+            Ok(error) => return error,
+            Err(error) => error,
+        };
+        let permission_denied = error.chain().any(|cause| {
+            cause
+                .downcast_ref::<std::io::Error>()
+                .is_some_and(|e| e.kind() == std::io::ErrorKind::PermissionDenied)
+        });
+        if permission_denied {
+            InspectError::PermissionDenied(format!("{:#}", error))
+        } else {
+            InspectError::BackendUnavailable(format!("{:#}", error))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_anyhow() {
+        let error = InspectError::from(anyhow::Error::new(InspectError::NoDisplay));
+        assert!(matches!(error, InspectError::NoDisplay));
+
+        let io = std::io::Error::from(std::io::ErrorKind::PermissionDenied);
+        let error = InspectError::from(anyhow::Error::new(io).context("Cannot open /proc/1/exe"));
+        assert!(matches!(error, InspectError::PermissionDenied(_)));
+
+        let error = InspectError::from(anyhow::anyhow!("No wlr manager"));
+        assert!(
+            matches!(error, InspectError::BackendUnavailable(reason) if reason == "No wlr manager")
+        );
+    }
+}
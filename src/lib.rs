@@ -1,34 +1,122 @@
 mod browser;
 mod device;
+mod error;
 mod native_app;
+mod site;
+#[cfg(test)]
+mod test_util;
 mod types;
-use browser::get_browser_active_tab_url;
+use browser::{get_browser_active_tab, get_devtools_active_tab, get_extension_active_tab};
 use log::debug;
-pub use {device::*, types::WindowInformation};
+pub use {
+    browser::{
+        load_or_create_token, start_tab_listener, BrowserIdentification, IdentificationSignal,
+        TabListener, TabUpdate,
+    },
+    device::*,
+    error::InspectError,
+    native_app::{
+        detect_backends, get_process_info, parse_remote_target, reset_backend, set_backend,
+        WindowBackend, BACKEND_ENV,
+    },
+    site::{normalize_url, NormalizedUrl, UrlKind},
+    types::{
+        Browser, BrowserTab, DesktopEntry, Geometry, Multiplexer, ProcessInfo, RemoteProtocol,
+        RemoteTarget, TabSource, TerminalPane, WindowEvent, WindowInformation, WindowState,
+        WindowType, Workspace,
+    },
+};
+use std::{
+    sync::mpsc::{self, Receiver},
+    thread,
+};
+
+/// Focused window, `Ok(None)` when no window has the focus (e.g. on an empty workspace).
+pub fn get_current_window_information() -> Result<Option<WindowInformation>, InspectError> {
+    let Some(mut window) = native_app::get_current_window_information()? else {
+        return Ok(None);
+    };
+    fill_details(&mut window);
+    fill_browser_tab(&mut window);
+    Ok(Some(window))
+}
+
+/// Every window known to the backend, without their browser tabs.
+pub fn list_windows() -> Result<Vec<WindowInformation>, InspectError> {
+    let mut windows = native_app::list_windows()?;
+    windows.iter_mut().for_each(fill_details);
+    Ok(windows)
+}
+
+/// Icon of `window` as PNG bytes, from `_NET_WM_ICON` at the size closest to `size` pixels,
+/// or else the themed icon of its desktop entry. `Ok(None)` when neither is available.
+pub fn get_window_icon(
+    window: &WindowInformation,
+    size: u32,
+) -> Result<Option<Vec<u8>>, InspectError> {
+    if let Some(png) = native_app::get_window_icon(window, size)? {
+        return Ok(Some(png));
+    }
+    let themed = window.desktop_entry.as_ref().and_then(|entry| entry.icon.as_deref());
+    Ok(themed.and_then(|name| native_app::find_theme_icon(name, size)))
+}
+
+/// Receive an event each time the focused window or its title changes, instead of polling
+/// `get_current_window_information`. Browser tabs are filled in as well.
+/// Stop listening by dropping the receiver.
+pub fn subscribe() -> Result<Receiver<WindowEvent>, InspectError> {
+    let native_events = native_app::subscribe()?;
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        for mut event in native_events {
+            fill_details(event.window_mut());
+            fill_browser_tab(event.window_mut());
+            if tx.send(event).is_err() {
+                break;
+            }
+        }
+    });
+    Ok(rx)
+}
 
-pub fn get_current_window_information() -> Option<WindowInformation> {
-    let mut window = native_app::get_current_window_information()?;
+/// Process, terminal and application details, from the PID and the class of the window.
+fn fill_details(window: &mut WindowInformation) {
+    window.process = window.pid.and_then(get_process_info);
+    window.foreground_process = window
+        .process
+        .as_ref()
+        .and_then(native_app::get_terminal_foreground);
+    window.pane = window
+        .foreground_process
+        .as_ref()
+        .and_then(native_app::get_active_pane);
+    window.remote_target = native_app::find_remote_target(window);
+    window.desktop_entry = native_app::get_desktop_entry(window);
+}
 
+fn fill_browser_tab(window: &mut WindowInformation) {
     let browser = window.get_browser_type();
     if let Some(b) = browser {
         debug!("Browser: {:?}", b);
-        window.url = get_browser_active_tab_url(b);
+        // Prefer real-time sources, session files lag behind
+        let title = window.title.as_deref().unwrap_or_default();
+        window.tab = get_extension_active_tab(&b)
+            .or_else(|| get_devtools_active_tab(&b, window.pid, title))
+            .or_else(|| get_browser_active_tab(b, window.pid));
+        window.url = window.tab.as_ref().map(|t| t.url.clone());
     }
-
-    Some(window)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use env_logger;
     use {std::thread::sleep, std::time::Duration};
 
     #[test]
     fn test_get_current_window_information() {
         env_logger::init();
         sleep(Duration::from_secs(2));
-        let window_info = get_current_window_information();
+        let window_info = get_current_window_information().unwrap();
         assert!(window_info.is_some());
         println!("{:?}", window_info.unwrap());
     }
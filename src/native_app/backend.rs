@@ -0,0 +1,257 @@
+// Runtime selection of the window backend. On Linux the right backend depends on the session
+// (X11, wlroots compositor, GNOME, Plasma, ...), which is only known at runtime, so the
+// candidates are detected from the environment and tried in order until one answers.
+// The first backend that answers is kept for the next calls.
+use {
+    crate::{InspectError, WindowEvent, WindowInformation},
+    anyhow::{anyhow, Result},
+    std::sync::mpsc::Receiver,
+    std::sync::Mutex,
+};
+
+/// Environment variable forcing a built-in backend, e.g. `SCREEN_INSPECTOR_BACKEND=x11`.
+pub const BACKEND_ENV: &str = "SCREEN_INSPECTOR_BACKEND";
+
+/// Source of window information. Implement it to plug a backend the crate doesn't ship,
+/// and install it with `set_backend`.
+pub trait WindowBackend: Send {
+    /// Short name, used in logs and matched against `SCREEN_INSPECTOR_BACKEND`.
+    fn name(&self) -> &'static str;
+
+    /// Focused window, `None` when no window has the focus.
+    /// Fails if the backend can't run in this session.
+    fn current(&self) -> Result<Option<WindowInformation>>;
+
+    /// Receive an event each time the focused window or its title changes.
+    /// Fails with `InspectError::Unsupported` unless the backend implements it.
+    fn subscribe(&self) -> Result<Receiver<WindowEvent>> {
+        Err(unsupported(format!(
+            "{} backend can't subscribe to window changes",
+            self.name()
+        )))
+    }
+
+    /// Every window the backend knows of.
+    /// Fails with `InspectError::Unsupported` unless the backend implements it.
+    fn list_windows(&self) -> Result<Vec<WindowInformation>> {
+        Err(unsupported(format!(
+            "{} backend can't list windows",
+            self.name()
+        )))
+    }
+
+    /// Icon of a window as PNG, at the size closest to `size` pixels.
+    /// `None` when the backend doesn't expose window icons.
+    fn icon(&self, _window: &WindowInformation, _size: u32) -> Result<Option<Vec<u8>>> {
+        Ok(None)
+    }
+}
+
+/// Error of a backend that can't do an operation, see `InspectError::Unsupported`.
+fn unsupported(reason: String) -> anyhow::Error {
+    anyhow::Error::new(InspectError::Unsupported(reason))
+}
+
+struct Selection {
+    backend: Box<dyn WindowBackend>,
+    /// Installed with `set_backend`, never replaced by the detection.
+    explicit: bool,
+}
+
+static SELECTED: Mutex<Option<Selection>> = Mutex::new(None);
+
+/// Use `backend` for every following call instead of the detected one.
+pub fn set_backend(backend: Box<dyn WindowBackend>) {
+    if let Ok(mut selected) = SELECTED.lock() {
+        *selected = Some(Selection {
+            backend,
+            explicit: true,
+        });
+    }
+}
+
+/// Go back to the detection after `set_backend`.
+pub fn reset_backend() {
+    if let Ok(mut selected) = SELECTED.lock() {
+        *selected = None;
+    }
+}
+
+/// Run `f` on the selected backend, or on the detected candidates in order until one succeeds,
+/// which is then kept. A kept backend that fails is dropped and the detection runs again.
+/// A backend that can't do the operation stays selected and its `Unsupported` error is
+/// returned as is: other backends of the session would only see part of the windows.
+/// When every candidate fails, a permission error is reported first, then the error of the
+/// most specific candidate.
+pub fn with_backend<T>(
+    f: impl Fn(&dyn WindowBackend) -> Result<T>,
+) -> std::result::Result<T, InspectError> {
+    let mut selected = SELECTED
+        .lock()
+        .map_err(|_| InspectError::BackendUnavailable("Backend selection poisoned".into()))?;
+    if let Some(selection) = selected.as_ref() {
+        match f(selection.backend.as_ref()).map_err(InspectError::from) {
+            Ok(value) => return Ok(value),
+            Err(e) if selection.explicit => return Err(e),
+            Err(e @ InspectError::Unsupported(_)) => return Err(e),
+            Err(e) => {
+                log::debug!("{} backend failed: {}", selection.backend.name(), e);
+                *selected = None;
+            }
+        }
+    }
+
+    let mut errors = Vec::new();
+    for backend in detect_backends()? {
+        match f(backend.as_ref()).map_err(InspectError::from) {
+            Ok(value) => {
+                log::debug!("Using {} backend", backend.name());
+                *selected = Some(Selection {
+                    backend,
+                    explicit: false,
+                });
+                return Ok(value);
+            }
+            // Only fall through when the backend doesn't run in this session either
+            Err(e @ InspectError::Unsupported(_)) if backend.current().is_ok() => {
+                log::debug!("Using {} backend", backend.name());
+                *selected = Some(Selection {
+                    backend,
+                    explicit: false,
+                });
+                return Err(e);
+            }
+            Err(e) => {
+                log::debug!("{} backend unavailable: {}", backend.name(), e);
+                errors.push(e);
+            }
+        }
+    }
+    let permission_denied = errors
+        .iter()
+        .position(|e| matches!(e, InspectError::PermissionDenied(_)));
+    match permission_denied {
+        Some(index) => Err(errors.swap_remove(index)),
+        None => Err(errors.into_iter().next().unwrap_or_else(|| {
+            InspectError::BackendUnavailable("No window backend available".into())
+        })),
+    }
+}
+
+/// Built-in backends for this session, most specific first.
+pub fn detect_backends() -> Result<Vec<Box<dyn WindowBackend>>> {
+    let mut candidates = builtin_backends();
+    if let Ok(name) = std::env::var(BACKEND_ENV) {
+        candidates.retain(|b| b.name() == name);
+        if candidates.is_empty() {
+            return Err(anyhow!("Unknown backend {}={}", BACKEND_ENV, name));
+        }
+    }
+    Ok(candidates)
+}
+
+#[cfg(target_os = "linux")]
+fn builtin_backends() -> Vec<Box<dyn WindowBackend>> {
+    use super::{gnome, hyprland, kwin, linux, sway, wayland};
+
+    let var = |name| std::env::var(name).unwrap_or_default();
+    let forced = std::env::var(BACKEND_ENV).is_ok();
+    let wayland_display = std::env::var_os("WAYLAND_DISPLAY").is_some();
+    let wayland_session = var("XDG_SESSION_TYPE") == "wayland" || wayland_display;
+    let desktops = var("XDG_CURRENT_DESKTOP");
+    let desktop = |name| desktops.split(':').any(|d| d == name);
+
+    let mut backends: Vec<Box<dyn WindowBackend>> = Vec::new();
+    if forced || std::env::var_os("SWAYSOCK").is_some() || std::env::var_os("I3SOCK").is_some() {
+        backends.push(Box::new(sway::SwayBackend));
+    }
+    if forced || std::env::var_os("HYPRLAND_INSTANCE_SIGNATURE").is_some() {
+        backends.push(Box::new(hyprland::HyprlandBackend));
+    }
+    if forced || (wayland_session && desktop("GNOME")) {
+        backends.push(Box::new(gnome::GnomeBackend));
+    }
+    if forced || (wayland_session && desktop("KDE")) {
+        backends.push(Box::new(kwin::KWinBackend));
+    }
+    if forced || wayland_session {
+        backends.push(Box::new(wayland::WlrBackend));
+    }
+    if forced || x11_session(wayland_display, &var("XDG_SESSION_TYPE")) {
+        backends.push(Box::new(linux::X11Backend));
+    }
+    backends
+}
+
+/// Whether X11 shows the focused window. On Wayland it only sees the XWayland windows, and
+/// would report one of them while a native window has the focus.
+#[cfg(target_os = "linux")]
+fn x11_session(wayland_display: bool, session_type: &str) -> bool {
+    !wayland_display || session_type == "x11"
+}
+
+#[cfg(target_os = "windows")]
+fn builtin_backends() -> Vec<Box<dyn WindowBackend>> {
+    vec![Box::new(super::windows::WindowsBackend)]
+}
+
+#[cfg(target_os = "macos")]
+fn builtin_backends() -> Vec<Box<dyn WindowBackend>> {
+    vec![Box::new(super::darwin::DarwinBackend)]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct FixedBackend(&'static str);
+
+    impl WindowBackend for FixedBackend {
+        fn name(&self) -> &'static str {
+            "fixed"
+        }
+
+        fn current(&self) -> Result<Option<WindowInformation>> {
+            Ok(Some(WindowInformation {
+                title: Some(self.0.to_string()),
+                ..Default::default()
+            }))
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_x11_session() {
+        assert!(x11_session(false, ""));
+        assert!(x11_session(false, "x11"));
+        // Nested Wayland compositor in an X11 session
+        assert!(x11_session(true, "x11"));
+        assert!(!x11_session(true, "wayland"));
+        assert!(!x11_session(true, ""));
+    }
+
+    #[test]
+    fn test_set_backend() {
+        set_backend(Box::new(FixedBackend("Plugged in")));
+        let window = with_backend(|b| b.current()).unwrap().unwrap();
+        assert_eq!(window.title.as_deref(), Some("Plugged in"));
+        // Not implemented by the backend, and no fallback to the detected ones
+        assert!(matches!(
+            with_backend(|b| b.list_windows()),
+            Err(InspectError::Unsupported(_))
+        ));
+        reset_backend();
+
+        // A detected backend stays selected too
+        *SELECTED.lock().unwrap() = Some(Selection {
+            backend: Box::new(FixedBackend("Detected")),
+            explicit: false,
+        });
+        assert!(matches!(
+            with_backend(|b| b.list_windows()),
+            Err(InspectError::Unsupported(_))
+        ));
+        let selected = SELECTED.lock().unwrap().take();
+        assert_eq!(selected.map(|s| s.backend.name()), Some("fixed"));
+    }
+}
@@ -51,7 +51,7 @@ pub fn get_current_window_information() -> Option<WindowInformation> {
         Some(dict_ref) => {
             let mut window_info = WindowInformation::default();
             if let Some(title) = get_dict_title(dict_ref) {
-                window_info.title = title;
+                window_info.title = Some(title);
             }
 
             if let Some(class) = get_dict_class(dict_ref) {
@@ -64,6 +64,7 @@ pub fn get_current_window_information() -> Option<WindowInformation> {
             };
 
             window_info.execpath = exec_path;
+            window_info.pid = Some(get_application_pid(active_app) as u32);
             window_info.time = unix_ts.as_secs();
             return Some(window_info);
         }
@@ -192,4 +193,17 @@ pub fn nsstring_to_rust_string(nsstring: *mut Object) -> String {
             "".into()
         }
     }
-}
\ No newline at end of file
+}
+#[cfg(target_os = "macos")]
+pub struct DarwinBackend;
+
+#[cfg(target_os = "macos")]
+impl super::WindowBackend for DarwinBackend {
+    fn name(&self) -> &'static str {
+        "macos"
+    }
+
+    fn current(&self) -> anyhow::Result<Option<WindowInformation>> {
+        Ok(get_current_window_information())
+    }
+}
@@ -0,0 +1,401 @@
+// Freedesktop desktop entries (.desktop files) of windows, for a readable name, icon and
+// categories instead of raw WM_CLASS or app ids.
+// Entries are read from the `applications` directory of $XDG_DATA_HOME and $XDG_DATA_DIRS,
+// plus the Flatpak exports, earlier directories hiding the entries of later ones with the same
+// desktop file id (the path under `applications`, with '/' replaced by '-').
+// A window is matched, from the most to the least reliable signal, by:
+// 1. the desktop file GIO launched the process from
+// 2. its app id: `_GTK_APPLICATION_ID`, the Wayland app id, or the Flatpak id from
+//    /proc/<pid>/root/.flatpak-info, as the desktop file id
+// 3. `StartupWMClass` equal to the WM_CLASS instance or class
+// 4. the WM_CLASS as desktop file id, e.g. "firefox" for firefox.desktop
+// 5. the executable name in `Exec`
+#![cfg(target_os = "linux")]
+use {
+    crate::{browser::get_desktop_id, types::DesktopEntry, WindowInformation},
+    std::{
+        collections::HashSet,
+        fs,
+        path::{Path, PathBuf},
+        sync::Mutex,
+        time::SystemTime,
+    },
+};
+
+struct Cache {
+    /// Latest mtime of the application directories when the entries were read.
+    modified: Option<SystemTime>,
+    entries: Vec<DesktopEntry>,
+}
+
+static CACHE: Mutex<Option<Cache>> = Mutex::new(None);
+
+/// What a window tells about the application it belongs to.
+#[derive(Debug, Default)]
+struct Lookup {
+    desktop_id: Option<String>,
+    app_ids: Vec<String>,
+    classes: Vec<String>,
+    exec_name: Option<String>,
+}
+
+/// Desktop entry of the application owning `window`.
+pub fn get_desktop_entry(window: &WindowInformation) -> Option<DesktopEntry> {
+    let mut app_ids: Vec<String> = window.application_id.iter().cloned().collect();
+    app_ids.extend(window.pid.and_then(get_flatpak_id));
+    let lookup = Lookup {
+        desktop_id: window.pid.and_then(get_desktop_id),
+        app_ids,
+        // Wayland backends report the app id as the class
+        classes: window.class.clone(),
+        exec_name: window
+            .execpath
+            .as_deref()
+            .and_then(|path| Some(Path::new(path).file_name()?.to_str()?.to_string())),
+    };
+
+    let dirs = application_dirs();
+    let modified = dirs
+        .iter()
+        .filter_map(|dir| fs::metadata(dir).ok()?.modified().ok())
+        .max();
+    let mut cache = CACHE.lock().ok()?;
+    if cache.as_ref().is_none_or(|c| c.modified != modified) {
+        *cache = Some(Cache {
+            modified,
+            entries: load_entries(&dirs, &locale()),
+        });
+    }
+    find_entry(&cache.as_ref()?.entries, &lookup).cloned()
+}
+
+/// `applications` directories, most important first.
+fn application_dirs() -> Vec<PathBuf> {
+    data_dirs()
+        .into_iter()
+        .map(|dir| dir.join("applications"))
+        .collect()
+}
+
+/// XDG data directories and Flatpak exports, most important first.
+pub fn data_dirs() -> Vec<PathBuf> {
+    let data_home = std::env::var_os("XDG_DATA_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| dirs::home_dir().map(|home| home.join(".local/share")));
+    let data_dirs = std::env::var("XDG_DATA_DIRS")
+        .ok()
+        .filter(|dirs| !dirs.is_empty())
+        .unwrap_or_else(|| "/usr/local/share:/usr/share".to_string());
+    let flatpak = [
+        data_home
+            .as_ref()
+            .map(|home| home.join("flatpak/exports/share")),
+        Some(PathBuf::from("/var/lib/flatpak/exports/share")),
+    ];
+
+    let mut dirs = Vec::new();
+    let all = data_home
+        .into_iter()
+        .chain(data_dirs.split(':').map(PathBuf::from))
+        .chain(flatpak.into_iter().flatten());
+    for dir in all {
+        if !dirs.contains(&dir) {
+            dirs.push(dir);
+        }
+    }
+    dirs
+}
+
+fn load_entries(dirs: &[PathBuf], locale: &str) -> Vec<DesktopEntry> {
+    let mut entries = Vec::new();
+    let mut ids = HashSet::new();
+    for dir in dirs {
+        let mut files = Vec::new();
+        find_desktop_files(dir, &mut files);
+        for file in files {
+            let Some(id) = desktop_file_id(dir, &file) else {
+                continue;
+            };
+            // The first directory defining an id wins, even with a hidden entry
+            if !ids.insert(id.clone()) {
+                continue;
+            }
+            let Ok(content) = fs::read_to_string(&file) else {
+                continue;
+            };
+            if let Some(entry) = parse_desktop_entry(&content, id, &file, locale) {
+                entries.push(entry);
+            }
+        }
+    }
+    entries
+}
+
+fn find_desktop_files(dir: &Path, files: &mut Vec<PathBuf>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            find_desktop_files(&path, files);
+        } else if path.extension().is_some_and(|ext| ext == "desktop") {
+            files.push(path);
+        }
+    }
+}
+
+/// `applications/kde4/kate.desktop` has the id `kde4-kate.desktop`.
+fn desktop_file_id(dir: &Path, file: &Path) -> Option<String> {
+    let relative = file.strip_prefix(dir).ok()?.to_str()?;
+    Some(relative.replace('/', "-"))
+}
+
+/// Keys of the [Desktop Entry] group. `None` for hidden entries and non applications.
+fn parse_desktop_entry(
+    content: &str,
+    id: String,
+    path: &Path,
+    locale: &str,
+) -> Option<DesktopEntry> {
+    let mut in_group = false;
+    let mut keys = Vec::new();
+    for line in content.lines() {
+        let line = line.trim();
+        if line.starts_with('[') {
+            in_group = line == "[Desktop Entry]";
+        } else if let Some((key, value)) = line.split_once('=').filter(|_| in_group) {
+            keys.push((key.trim(), value.trim()));
+        }
+    }
+    let value = |key: &str| keys.iter().find(|(k, _)| *k == key).map(|(_, v)| *v);
+    // Name[vi_VN], then Name[vi], then Name
+    let localized = |key: &str| {
+        let mut found = None;
+        if !locale.is_empty() {
+            let language = locale.split('_').next().unwrap_or(locale);
+            found = value(&format!("{}[{}]", key, locale))
+                .or_else(|| value(&format!("{}[{}]", key, language)));
+        }
+        found.or_else(|| value(key)).map(unescape)
+    };
+
+    if value("Type") != Some("Application") || value("Hidden") == Some("true") {
+        return None;
+    }
+    Some(DesktopEntry {
+        id,
+        path: path.display().to_string(),
+        name: localized("Name"),
+        generic_name: localized("GenericName"),
+        icon: value("Icon").map(unescape),
+        categories: value("Categories")
+            .map(|categories| {
+                categories
+                    .split(';')
+                    .filter(|c| !c.is_empty())
+                    .map(str::to_string)
+                    .collect()
+            })
+            .unwrap_or_default(),
+        exec: value("Exec").map(unescape),
+        startup_wm_class: value("StartupWMClass").map(unescape),
+    })
+}
+
+fn find_entry<'a>(entries: &'a [DesktopEntry], lookup: &Lookup) -> Option<&'a DesktopEntry> {
+    let by_id = |id: &str| {
+        let id = id.strip_suffix(".desktop").unwrap_or(id);
+        entries
+            .iter()
+            .find(|entry| entry.id.strip_suffix(".desktop") == Some(id))
+    };
+    let by_id_ignore_case = |id: &str| {
+        entries.iter().find(|entry| {
+            let entry_id = entry.id.strip_suffix(".desktop").unwrap_or(&entry.id);
+            entry_id.eq_ignore_ascii_case(id)
+        })
+    };
+
+    lookup
+        .desktop_id
+        .as_deref()
+        .and_then(by_id)
+        .or_else(|| lookup.app_ids.iter().find_map(|id| by_id(id)))
+        .or_else(|| {
+            lookup.classes.iter().find_map(|class| {
+                entries.iter().find(|entry| {
+                    entry
+                        .startup_wm_class
+                        .as_deref()
+                        .is_some_and(|wm_class| wm_class.eq_ignore_ascii_case(class))
+                })
+            })
+        })
+        .or_else(|| {
+            lookup
+                .classes
+                .iter()
+                .find_map(|class| by_id_ignore_case(class))
+        })
+        .or_else(|| {
+            let exec_name = lookup.exec_name.as_deref()?;
+            entries
+                .iter()
+                .find(|entry| entry.exec.as_deref().and_then(exec_program) == Some(exec_name))
+        })
+}
+
+/// File name of the program an `Exec` key runs, skipping an `env VAR=value` prefix.
+fn exec_program(exec: &str) -> Option<&str> {
+    let mut words = exec.split_whitespace().map(|word| word.trim_matches('"'));
+    let mut program = words.next()?;
+    if Path::new(program).file_name()? == "env" {
+        program = words.find(|word| !word.contains('=') && !word.starts_with('-'))?;
+    }
+    Path::new(program).file_name()?.to_str()
+}
+
+/// Flatpak id of a sandboxed process, from the `.flatpak-info` at the root of its sandbox.
+fn get_flatpak_id(pid: u32) -> Option<String> {
+    let info = fs::read_to_string(format!("/proc/{}/root/.flatpak-info", pid)).ok()?;
+    info.lines()
+        .find_map(|line| line.strip_prefix("name="))
+        .map(|name| name.trim().to_string())
+}
+
+/// Language of the messages, e.g. "vi_VN" from LC_MESSAGES=vi_VN.UTF-8.
+fn locale() -> String {
+    let locale = ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .iter()
+        .find_map(|var| std::env::var(var).ok().filter(|value| !value.is_empty()))
+        .unwrap_or_default();
+    let locale = locale.split(['.', '@']).next().unwrap_or_default();
+    match locale {
+        "C" | "POSIX" => String::new(),
+        locale => locale.to_string(),
+    }
+}
+
+/// Escape sequences of string values in desktop files.
+fn unescape(value: &str) -> String {
+    let mut unescaped = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('s') => unescaped.push(' '),
+            Some('n') => unescaped.push('\n'),
+            Some('t') => unescaped.push('\t'),
+            Some('r') => unescaped.push('\r'),
+            Some(other) => unescaped.push(other),
+            None => unescaped.push('\\'),
+        }
+    }
+    unescaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+
+    const IDEA: &str = "[Desktop Entry]
+Type=Application
+Name=IntelliJ IDEA Community Edition
+Icon=/opt/idea/bin/idea.svg
+Exec=\"/opt/idea/bin/idea.sh\" %f
+Categories=Development;IDE;
+StartupWMClass=jetbrains-idea-ce
+
+[Desktop Action NewWindow]
+Name=New Window
+";
+
+    const NAUTILUS: &str = "[Desktop Entry]
+Type=Application
+Name=Files
+Name[vi]=Tập tin
+Icon=org.gnome.Nautilus
+Exec=nautilus --new-window %U
+Categories=GNOME;GTK;Utility;Core;FileManager;
+";
+
+    #[test]
+    fn test_parse_desktop_entry() {
+        let entry = parse_desktop_entry(
+            NAUTILUS,
+            "org.gnome.Nautilus.desktop".into(),
+            Path::new("/x"),
+            "vi_VN",
+        )
+        .unwrap();
+        assert_eq!(entry.name.as_deref(), Some("Tập tin"));
+        assert_eq!(entry.icon.as_deref(), Some("org.gnome.Nautilus"));
+        assert_eq!(
+            entry.categories,
+            ["GNOME", "GTK", "Utility", "Core", "FileManager"]
+        );
+
+        let entry = parse_desktop_entry(IDEA, "idea.desktop".into(), Path::new("/x"), "").unwrap();
+        // Keys of other groups are ignored
+        assert_eq!(
+            entry.name.as_deref(),
+            Some("IntelliJ IDEA Community Edition")
+        );
+        assert_eq!(
+            exec_program(entry.exec.as_deref().unwrap()),
+            Some("idea.sh")
+        );
+        assert_eq!(
+            exec_program("env BAMF_DESKTOP_FILE_HINT=/x.desktop /snap/bin/code --new-window"),
+            Some("code")
+        );
+    }
+
+    #[test]
+    fn test_find_entry() {
+        let dir = TempDir::new("apps");
+        dir.write("system/jetbrains-idea-ce.desktop", IDEA);
+        dir.write("system/org.gnome.Nautilus.desktop", NAUTILUS);
+        dir.write(
+            "system/kde4/kate.desktop",
+            "[Desktop Entry]\nType=Application\nName=Kate\nExec=kate -b %U\n",
+        );
+        // Hidden by the user, the system entry must not show up
+        dir.write(
+            "home/org.gnome.Nautilus.desktop",
+            "[Desktop Entry]\nType=Application\nHidden=true\n",
+        );
+
+        let (home, system) = (dir.path().join("home"), dir.path().join("system"));
+        let entries = load_entries(&[home, system], "");
+        let find = |lookup: Lookup| find_entry(&entries, &lookup).map(|entry| entry.id.as_str());
+
+        assert_eq!(
+            find(Lookup {
+                classes: vec!["jetbrains-idea-ce".into(), "jetbrains-idea-ce".into()],
+                ..Default::default()
+            }),
+            Some("jetbrains-idea-ce.desktop")
+        );
+        assert_eq!(
+            find(Lookup {
+                app_ids: vec!["org.gnome.Nautilus".into()],
+                ..Default::default()
+            }),
+            None
+        );
+        assert_eq!(
+            find(Lookup {
+                exec_name: Some("kate".into()),
+                ..Default::default()
+            }),
+            Some("kde4-kate.desktop")
+        );
+    }
+}
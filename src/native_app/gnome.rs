@@ -0,0 +1,329 @@
+// GNOME Shell backend. Mutter doesn't implement the wlr toplevel protocols and native Wayland
+// windows are invisible to X11, so the focused window is asked to the shell over the session bus:
+// - the companion extension (extensions/gnome-shell) exports `GetFocusedWindow` on the
+//   org.gnome.Shell connection, with title, WM_CLASS, PID, workspace and geometry
+// - without it, `org.gnome.Shell.Introspect.GetWindows` is tried. The shell only answers it for
+//   allowed callers (or in unsafe mode) and doesn't report PIDs nor workspaces.
+#![cfg(target_os = "linux")]
+use {
+    super::{get_exec_path, WindowBackend},
+    crate::{
+        types::{Geometry, WindowState, Workspace},
+        InspectError, WindowInformation,
+    },
+    anyhow::{anyhow, Result},
+    std::collections::HashMap,
+    std::time::SystemTime,
+    zbus::blocking::Connection,
+    zbus::zvariant::{OwnedValue, Value},
+};
+
+const SHELL_NAME: &str = "org.gnome.Shell";
+const EXTENSION_PATH: &str = "/io/github/phucvinh57/ScreenInspector";
+const EXTENSION_IFACE: &str = "io.github.phucvinh57.ScreenInspector";
+const INTROSPECT_PATH: &str = "/org/gnome/Shell/Introspect";
+const INTROSPECT_IFACE: &str = "org.gnome.Shell.Introspect";
+
+type Properties = HashMap<String, OwnedValue>;
+
+pub struct GnomeBackend;
+
+impl WindowBackend for GnomeBackend {
+    fn name(&self) -> &'static str {
+        "gnome"
+    }
+
+    fn current(&self) -> Result<Option<WindowInformation>> {
+        get_current_window_information()
+    }
+}
+
+pub fn get_current_window_information() -> Result<Option<WindowInformation>> {
+    let conn = Connection::session()?;
+    get_focused_window(&conn)
+}
+
+fn get_focused_window(conn: &Connection) -> Result<Option<WindowInformation>> {
+    match call_extension(conn) {
+        Ok(window) => Ok(window),
+        Err(e) => {
+            log::debug!("GNOME Shell extension unavailable: {}", e);
+            call_introspect(conn)
+        }
+    }
+}
+
+fn call_extension(conn: &Connection) -> Result<Option<WindowInformation>> {
+    let reply = conn.call_method(
+        Some(SHELL_NAME),
+        EXTENSION_PATH,
+        Some(EXTENSION_IFACE),
+        "GetFocusedWindow",
+        &(),
+    )?;
+    let properties: Properties = reply.body().deserialize()?;
+    if properties.is_empty() {
+        // No window has the focus
+        return Ok(None);
+    }
+    Ok(Some(window_from_extension(&properties)))
+}
+
+fn call_introspect(conn: &Connection) -> Result<Option<WindowInformation>> {
+    let reply = conn
+        .call_method(
+            Some(SHELL_NAME),
+            INTROSPECT_PATH,
+            Some(INTROSPECT_IFACE),
+            "GetWindows",
+            &(),
+        )
+        .map_err(|e| match e {
+            zbus::Error::MethodError(ref name, _, _)
+                if name.as_str() == "org.freedesktop.DBus.Error.AccessDenied" =>
+            {
+                anyhow::Error::new(InspectError::PermissionDenied(format!(
+                    "org.gnome.Shell.Introspect refused: {}",
+                    e
+                )))
+            }
+            e => anyhow!("org.gnome.Shell.Introspect unavailable: {}", e),
+        })?;
+    let windows: HashMap<u64, Properties> = reply.body().deserialize()?;
+    Ok(windows
+        .iter()
+        .find(|(_, properties)| get::<bool>(properties, "has-focus") == Some(true))
+        .map(|(id, properties)| window_from_introspect(*id, properties)))
+}
+
+fn get<'a, T>(properties: &'a Properties, key: &str) -> Option<T>
+where
+    T: TryFrom<&'a Value<'a>>,
+    zbus::zvariant::Error: From<T::Error>,
+{
+    properties.get(key)?.downcast_ref().ok()
+}
+
+fn get_string(properties: &Properties, key: &str) -> Option<String> {
+    get::<&str>(properties, key).map(str::to_string)
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+fn window_from_extension(properties: &Properties) -> WindowInformation {
+    // Same order as WM_CLASS: instance, then class
+    let class = ["wm_class_instance", "wm_class"]
+        .into_iter()
+        .filter_map(|key| get_string(properties, key))
+        .collect();
+
+    let pid = get::<u32>(properties, "pid");
+    let execpath = pid.and_then(get_exec_path);
+
+    let workspace_index = get::<u32>(properties, "workspace_index");
+    let workspace_name = get_string(properties, "workspace_name");
+    let workspace = (workspace_index.is_some() || workspace_name.is_some()).then_some(Workspace {
+        index: workspace_index,
+        name: workspace_name,
+    });
+
+    let flag = |key| get::<bool>(properties, key).unwrap_or_default();
+    WindowInformation {
+        time: now(),
+        title: get_string(properties, "title"),
+        class,
+        execpath,
+        pid,
+        id: get::<u64>(properties, "id"),
+        workspace,
+        geometry: Some(Geometry {
+            x: get(properties, "x").unwrap_or_default(),
+            y: get(properties, "y").unwrap_or_default(),
+            width: get(properties, "width").unwrap_or_default(),
+            height: get(properties, "height").unwrap_or_default(),
+        })
+        .filter(|g| g.width > 0 && g.height > 0),
+        state: Some(WindowState {
+            fullscreen: flag("fullscreen"),
+            maximized: flag("maximized"),
+            hidden: flag("hidden"),
+            above: flag("above"),
+            demands_attention: flag("demands_attention"),
+        }),
+        xwayland: get::<bool>(properties, "xwayland"),
+        ..Default::default()
+    }
+}
+
+/// Introspect reports the app id (desktop file name) and the WM_CLASS, without position.
+fn window_from_introspect(id: u64, properties: &Properties) -> WindowInformation {
+    let class = get_string(properties, "wm-class")
+        .or_else(|| get_string(properties, "app-id"))
+        .into_iter()
+        .collect();
+    WindowInformation {
+        time: now(),
+        title: get_string(properties, "title"),
+        class,
+        id: Some(id),
+        ..Default::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::start_dbus_daemon;
+
+    struct MockExtension(Properties);
+
+    #[zbus::interface(name = "io.github.phucvinh57.ScreenInspector")]
+    impl MockExtension {
+        fn get_focused_window(&self) -> Properties {
+            self.0
+                .iter()
+                .map(|(k, v)| (k.clone(), v.try_clone().unwrap()))
+                .collect()
+        }
+    }
+
+    struct MockIntrospect;
+
+    #[zbus::interface(name = "org.gnome.Shell.Introspect")]
+    impl MockIntrospect {
+        fn get_windows(&self) -> HashMap<u64, Properties> {
+            let window = |title: &str, focused: bool| -> Properties {
+                HashMap::from([
+                    ("title".to_string(), text(title)),
+                    ("wm-class".to_string(), text("org.gnome.Nautilus")),
+                    ("has-focus".to_string(), OwnedValue::from(focused)),
+                ])
+            };
+            HashMap::from([(11, window("Home", false)), (12, window("Downloads", true))])
+        }
+    }
+
+    /// GNOME Shell outside of unsafe mode
+    struct DeniedIntrospect;
+
+    #[zbus::interface(name = "org.gnome.Shell.Introspect")]
+    impl DeniedIntrospect {
+        fn get_windows(&self) -> zbus::fdo::Result<HashMap<u64, Properties>> {
+            Err(zbus::fdo::Error::AccessDenied(
+                "App introspection not allowed".into(),
+            ))
+        }
+    }
+
+    fn text(s: &str) -> OwnedValue {
+        OwnedValue::try_from(Value::from(s)).unwrap()
+    }
+
+    fn connect(address: &str) -> Connection {
+        zbus::blocking::connection::Builder::address(address)
+            .unwrap()
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_extension() {
+        let Some((_daemon, address)) = start_dbus_daemon() else {
+            eprintln!("dbus-daemon not available, skipping");
+            return;
+        };
+        let pid = std::process::id();
+        let properties: Properties = HashMap::from([
+            ("id".to_string(), OwnedValue::from(42u64)),
+            ("title".to_string(), text("README.md - Visual Studio Code")),
+            ("wm_class".to_string(), text("Code")),
+            ("wm_class_instance".to_string(), text("code")),
+            ("pid".to_string(), OwnedValue::from(pid)),
+            ("workspace_index".to_string(), OwnedValue::from(1u32)),
+            ("workspace_name".to_string(), text("Workspace 2")),
+            ("x".to_string(), OwnedValue::from(0i32)),
+            ("y".to_string(), OwnedValue::from(32i32)),
+            ("width".to_string(), OwnedValue::from(1920u32)),
+            ("height".to_string(), OwnedValue::from(1048u32)),
+            ("maximized".to_string(), OwnedValue::from(true)),
+            ("xwayland".to_string(), OwnedValue::from(true)),
+        ]);
+        let _server = zbus::blocking::connection::Builder::address(address.as_str())
+            .unwrap()
+            .name(SHELL_NAME)
+            .unwrap()
+            .serve_at(EXTENSION_PATH, MockExtension(properties))
+            .unwrap()
+            .build()
+            .unwrap();
+
+        let window = get_focused_window(&connect(&address)).unwrap().unwrap();
+        assert_eq!(
+            window.title.as_deref(),
+            Some("README.md - Visual Studio Code")
+        );
+        assert_eq!(window.class, vec!["code", "Code"]);
+        assert_eq!(window.pid, Some(pid));
+        assert!(window.execpath.is_some());
+        assert_eq!(window.id, Some(42));
+        assert_eq!(
+            window.workspace,
+            Some(Workspace {
+                index: Some(1),
+                name: Some("Workspace 2".to_string())
+            })
+        );
+        assert_eq!(window.geometry.unwrap().height, 1048);
+        assert!(window.state.unwrap().maximized);
+        assert_eq!(window.xwayland, Some(true));
+    }
+
+    #[test]
+    fn test_introspect_fallback() {
+        let Some((_daemon, address)) = start_dbus_daemon() else {
+            eprintln!("dbus-daemon not available, skipping");
+            return;
+        };
+        let _server = zbus::blocking::connection::Builder::address(address.as_str())
+            .unwrap()
+            .name(SHELL_NAME)
+            .unwrap()
+            .serve_at(INTROSPECT_PATH, MockIntrospect)
+            .unwrap()
+            .build()
+            .unwrap();
+
+        let window = get_focused_window(&connect(&address)).unwrap().unwrap();
+        assert_eq!(window.title.as_deref(), Some("Downloads"));
+        assert_eq!(window.class, vec!["org.gnome.Nautilus"]);
+        assert_eq!(window.id, Some(12));
+        assert!(window.pid.is_none());
+    }
+
+    #[test]
+    fn test_introspect_denied() {
+        let Some((_daemon, address)) = start_dbus_daemon() else {
+            eprintln!("dbus-daemon not available, skipping");
+            return;
+        };
+        let _server = zbus::blocking::connection::Builder::address(address.as_str())
+            .unwrap()
+            .name(SHELL_NAME)
+            .unwrap()
+            .serve_at(INTROSPECT_PATH, DeniedIntrospect)
+            .unwrap()
+            .build()
+            .unwrap();
+
+        let error = get_focused_window(&connect(&address)).unwrap_err();
+        assert!(matches!(
+            InspectError::from(error),
+            InspectError::PermissionDenied(_)
+        ));
+    }
+}
@@ -0,0 +1,382 @@
+// Hyprland backend over its IPC sockets in $XDG_RUNTIME_DIR/hypr/$HYPRLAND_INSTANCE_SIGNATURE
+// (/tmp/hypr/... before Hyprland 0.40):
+// - .socket.sock: one command per connection, e.g. "j/activewindow" answers the focused window
+//   as JSON and closes the connection. "j/monitors" maps the monitor id of the window to a name.
+// - .socket2.sock: a stream of "EVENT>>DATA" lines. "activewindowv2>>ADDRESS" is sent on every
+//   focus change and "windowtitle>>ADDRESS" when a window changes its title.
+#![cfg(target_os = "linux")]
+use {
+    super::{get_exec_path, WindowBackend},
+    crate::{
+        types::{Geometry, WindowState, Workspace},
+        WindowEvent, WindowInformation,
+    },
+    anyhow::{anyhow, Result},
+    serde_json::Value,
+    std::io::{BufRead, BufReader, Read, Write},
+    std::os::unix::net::UnixStream,
+    std::path::{Path, PathBuf},
+    std::sync::mpsc::{self, Receiver, Sender},
+    std::thread,
+    std::time::SystemTime,
+};
+
+pub struct HyprlandBackend;
+
+impl WindowBackend for HyprlandBackend {
+    fn name(&self) -> &'static str {
+        "hyprland"
+    }
+
+    fn current(&self) -> Result<Option<WindowInformation>> {
+        get_current_window_information()
+    }
+
+    fn subscribe(&self) -> Result<Receiver<WindowEvent>> {
+        subscribe()
+    }
+
+    fn list_windows(&self) -> Result<Vec<WindowInformation>> {
+        let dir = socket_dir().ok_or(anyhow!("HYPRLAND_INSTANCE_SIGNATURE is not set"))?;
+        list_clients(&dir)
+    }
+}
+
+/// Directory holding the sockets of the running Hyprland instance.
+pub fn socket_dir() -> Option<PathBuf> {
+    let signature = std::env::var_os("HYPRLAND_INSTANCE_SIGNATURE")?;
+    let runtime_dir = std::env::var_os("XDG_RUNTIME_DIR")
+        .map(|dir| PathBuf::from(dir).join("hypr").join(&signature))
+        .filter(|dir| dir.exists());
+    Some(runtime_dir.unwrap_or_else(|| Path::new("/tmp/hypr").join(signature)))
+}
+
+pub fn get_current_window_information() -> Result<Option<WindowInformation>> {
+    let dir = socket_dir().ok_or(anyhow!("HYPRLAND_INSTANCE_SIGNATURE is not set"))?;
+    get_active_window(&dir)
+}
+
+/// Receive an event each time the focused window or its title changes.
+/// The watching thread stops once the receiver is dropped and the next event arrives.
+pub fn subscribe() -> Result<Receiver<WindowEvent>> {
+    let dir = socket_dir().ok_or(anyhow!("HYPRLAND_INSTANCE_SIGNATURE is not set"))?;
+    let events = connect(&dir.join(".socket2.sock"))?;
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        if let Err(e) = watch(&dir, events, tx) {
+            log::error!("Hyprland event loop stopped: {}", e);
+        }
+    });
+    Ok(rx)
+}
+
+fn watch(dir: &Path, events: UnixStream, tx: Sender<WindowEvent>) -> Result<()> {
+    let mut active: Option<String> = None;
+    let mut last_title: Option<String> = None;
+
+    for line in BufReader::new(events).lines() {
+        let line = line?;
+        let Some((event, data)) = line.split_once(">>") else {
+            continue;
+        };
+        let window_event = match event {
+            "activewindowv2" => {
+                // Empty when the focus goes to an empty workspace
+                let address = (!data.is_empty()).then(|| normalize_address(data));
+                if address == active {
+                    continue;
+                }
+                active = address;
+                last_title = None;
+                if active.is_none() {
+                    continue;
+                }
+                let Some(window) = get_active_window(dir)? else {
+                    continue;
+                };
+                last_title = window.title.clone();
+                WindowEvent::FocusChanged(window)
+            }
+            // windowtitlev2 also carries the title, the address is enough here
+            "windowtitle" | "windowtitlev2" => {
+                let address = data.split(',').next().map(normalize_address);
+                if active.is_none() || address != active {
+                    continue;
+                }
+                let Some(window) = get_active_window(dir)? else {
+                    continue;
+                };
+                if last_title == window.title {
+                    continue;
+                }
+                last_title = window.title.clone();
+                WindowEvent::TitleChanged(window)
+            }
+            _ => continue,
+        };
+        if tx.send(window_event).is_err() {
+            // Receiver dropped
+            return Ok(());
+        }
+    }
+    Err(anyhow!("Hyprland closed the event socket"))
+}
+
+/// Events write addresses without the "0x" prefix that `activewindow` has.
+fn normalize_address(address: &str) -> String {
+    address.trim_start_matches("0x").to_string()
+}
+
+fn connect(path: &Path) -> Result<UnixStream> {
+    UnixStream::connect(path).map_err(|e| anyhow!("Cannot connect to {}: {}", path.display(), e))
+}
+
+/// Send one command to .socket.sock and parse the JSON reply.
+fn request(dir: &Path, command: &str) -> Result<Value> {
+    let mut stream = connect(&dir.join(".socket.sock"))?;
+    stream.write_all(command.as_bytes())?;
+    let mut reply = Vec::new();
+    stream.read_to_end(&mut reply)?;
+    serde_json::from_slice(&reply).map_err(|e| anyhow!("Invalid reply to {}: {}", command, e))
+}
+
+fn get_active_window(dir: &Path) -> Result<Option<WindowInformation>> {
+    let window = request(dir, "j/activewindow")?;
+    // `{}` when no window has the focus
+    if window["address"].as_str().is_none() {
+        return Ok(None);
+    }
+    let monitors = match window["monitor"].as_i64() {
+        Some(_) => request(dir, "j/monitors")?,
+        None => Value::Null,
+    };
+    Ok(Some(window_from_json(&window, &monitors)))
+}
+
+fn list_clients(dir: &Path) -> Result<Vec<WindowInformation>> {
+    let clients = request(dir, "j/clients")?;
+    let monitors = request(dir, "j/monitors")?;
+    Ok(clients
+        .as_array()
+        .ok_or(anyhow!("Invalid reply to j/clients"))?
+        .iter()
+        // Unmapped clients are windows being created or destroyed
+        .filter(|client| client["mapped"] != Value::Bool(false))
+        .map(|client| window_from_json(client, &monitors))
+        .collect())
+}
+
+fn window_from_json(window: &Value, monitors: &Value) -> WindowInformation {
+    let time = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+
+    // Hyprland doesn't expose the WM_CLASS instance, only the class (the app_id on Wayland).
+    // The class the window was created with stands in when it has cleared it since.
+    let class = [&window["class"], &window["initialClass"]]
+        .into_iter()
+        .find_map(|v| v.as_str().filter(|s| !s.is_empty()).map(str::to_string))
+        .into_iter()
+        .collect();
+
+    let pid = window["pid"]
+        .as_i64()
+        .and_then(|pid| u32::try_from(pid).ok());
+    let execpath = pid.and_then(get_exec_path);
+
+    let output = window["monitor"].as_i64().and_then(|id| {
+        monitors
+            .as_array()?
+            .iter()
+            .find(|m| m["id"].as_i64() == Some(id))?["name"]
+            .as_str()
+            .map(str::to_string)
+    });
+
+    WindowInformation {
+        time,
+        title: window["title"].as_str().map(str::to_string),
+        class,
+        execpath,
+        pid,
+        id: window["address"]
+            .as_str()
+            .and_then(|a| u64::from_str_radix(&normalize_address(a), 16).ok()),
+        workspace: window["workspace"].is_object().then(|| Workspace {
+            // Special workspaces (scratchpads) have negative ids
+            index: window["workspace"]["id"]
+                .as_i64()
+                .and_then(|id| u32::try_from(id).ok()),
+            name: window["workspace"]["name"].as_str().map(str::to_string),
+        }),
+        output,
+        geometry: parse_geometry(&window["at"], &window["size"]),
+        state: Some(parse_state(window)),
+        xwayland: window["xwayland"].as_bool(),
+        ..Default::default()
+    }
+}
+
+fn parse_geometry(at: &Value, size: &Value) -> Option<Geometry> {
+    Some(Geometry {
+        x: at[0].as_i64()? as i32,
+        y: at[1].as_i64()? as i32,
+        width: size[0].as_u64()? as u32,
+        height: size[1].as_u64()? as u32,
+    })
+}
+
+/// `fullscreen` is a bool with `fullscreenMode` (0 fullscreen, 1 maximized) before Hyprland 0.42,
+/// and a bitmask (1 maximized, 2 fullscreen, 3 both) since.
+fn parse_state(window: &Value) -> WindowState {
+    let mode = match &window["fullscreen"] {
+        Value::Bool(false) => 0,
+        Value::Bool(true) if window["fullscreenMode"].as_i64() == Some(1) => 1,
+        Value::Bool(true) => 2,
+        value => value.as_i64().unwrap_or_default(),
+    };
+    WindowState {
+        fullscreen: mode & 2 != 0,
+        maximized: mode & 1 != 0,
+        hidden: window["hidden"].as_bool().unwrap_or_default(),
+        above: window["pinned"].as_bool().unwrap_or_default(),
+        ..Default::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+    use std::os::unix::net::UnixListener;
+
+    // Recorded with `hyprctl -j activewindow` and `hyprctl -j monitors`, trimmed
+    const ACTIVE_WINDOW: &str = r#"{
+        "address": "0x5a1c3e2b9f40", "mapped": true, "hidden": false,
+        "at": [1932, 52], "size": [1876, 1096],
+        "workspace": {"id": 3, "name": "3"}, "floating": false, "pseudo": false,
+        "monitor": 1, "class": "org.wezfurlong.wezterm", "title": "vim main.rs",
+        "initialClass": "org.wezfurlong.wezterm", "initialTitle": "wezterm",
+        "pid": 4242, "xwayland": false, "pinned": false, "fullscreen": 1,
+        "fullscreenClient": 1, "grouped": [], "tags": [], "swallowing": "0x0",
+        "focusHistoryID": 0
+    }"#;
+    const CLIENTS: &str = r#"[
+        {"address": "0x5a1c3e2b9f40", "mapped": true, "hidden": false,
+         "at": [1932, 52], "size": [1876, 1096], "workspace": {"id": 3, "name": "3"},
+         "monitor": 1, "class": "org.wezfurlong.wezterm", "title": "vim main.rs",
+         "initialClass": "org.wezfurlong.wezterm", "pid": 4242, "xwayland": false,
+         "fullscreen": 1},
+        {"address": "0x5a1c3e2c1a10", "mapped": true, "hidden": true,
+         "at": [10, 10], "size": [800, 600], "workspace": {"id": -98, "name": "special:magic"},
+         "monitor": 0, "class": "Spotify", "title": "Spotify Premium",
+         "initialClass": "Spotify", "pid": 4300, "xwayland": true, "fullscreen": 0}
+    ]"#;
+    const MONITORS: &str = r#"[
+        {"id": 0, "name": "eDP-1", "description": "BOE 0x0BCA", "width": 1920, "height": 1200},
+        {"id": 1, "name": "DP-2", "description": "Dell U2720Q", "width": 3840, "height": 2160}
+    ]"#;
+
+    /// Answer commands on .socket.sock, one per connection like Hyprland.
+    fn serve_commands(dir: &Path) {
+        let listener = UnixListener::bind(dir.join(".socket.sock")).unwrap();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut command = [0u8; 64];
+                let len = stream.read(&mut command).unwrap();
+                let reply = match &command[..len] {
+                    b"j/activewindow" => ACTIVE_WINDOW,
+                    b"j/monitors" => MONITORS,
+                    b"j/clients" => CLIENTS,
+                    _ => "unknown request",
+                };
+                stream.write_all(reply.as_bytes()).unwrap();
+            }
+        });
+    }
+
+    #[test]
+    fn test_get_active_window() {
+        let instance = TempDir::new("hypr-active");
+        let dir = instance.path();
+        serve_commands(dir);
+
+        let window = get_active_window(dir).unwrap().unwrap();
+        assert_eq!(window.title.as_deref(), Some("vim main.rs"));
+        assert_eq!(window.class, vec!["org.wezfurlong.wezterm"]);
+        assert_eq!(window.pid, Some(4242));
+        assert_eq!(window.id, Some(0x5a1c3e2b9f40));
+        assert_eq!(window.output.as_deref(), Some("DP-2"));
+        assert_eq!(window.workspace.unwrap().index, Some(3));
+        assert_eq!(
+            window.geometry,
+            Some(Geometry {
+                x: 1932,
+                y: 52,
+                width: 1876,
+                height: 1096
+            })
+        );
+        let state = window.state.unwrap();
+        assert!(state.maximized && !state.fullscreen);
+        // Maximized, then made fullscreen
+        let state = parse_state(&serde_json::json!({"fullscreen": 3}));
+        assert!(state.maximized && state.fullscreen);
+        assert_eq!(window.xwayland, Some(false));
+    }
+
+    #[test]
+    fn test_list_clients() {
+        let instance = TempDir::new("hypr-clients");
+        let dir = instance.path();
+        serve_commands(dir);
+
+        let windows = list_clients(dir).unwrap();
+        assert_eq!(windows.len(), 2);
+        let spotify = &windows[1];
+        assert_eq!(spotify.output.as_deref(), Some("eDP-1"));
+        let workspace = spotify.workspace.as_ref().unwrap();
+        assert_eq!(workspace.index, None);
+        assert_eq!(workspace.name.as_deref(), Some("special:magic"));
+        assert!(spotify.state.unwrap().hidden);
+        assert_eq!(spotify.class, vec!["Spotify"]);
+        // Class cleared after the window was mapped
+        let cleared = serde_json::json!({"class": "", "initialClass": "Spotify"});
+        assert_eq!(
+            window_from_json(&cleared, &serde_json::json!([])).class,
+            vec!["Spotify"]
+        );
+    }
+
+    #[test]
+    fn test_focus_events() {
+        let instance = TempDir::new("hypr-events");
+        let dir = instance.path();
+        serve_commands(dir);
+        let listener = UnixListener::bind(dir.join(".socket2.sock")).unwrap();
+        let events = connect(&dir.join(".socket2.sock")).unwrap();
+        let (mut stream, _) = listener.accept().unwrap();
+
+        let (tx, rx) = mpsc::channel();
+        let watch_dir = dir.to_path_buf();
+        thread::spawn(move || watch(&watch_dir, events, tx));
+
+        stream
+            .write_all(
+                b"workspacev2>>3,3\n\
+                  windowtitle>>5a1c3e2b9f40\n\
+                  activewindow>>org.wezfurlong.wezterm,vim main.rs\n\
+                  activewindowv2>>5a1c3e2b9f40\n",
+            )
+            .unwrap();
+
+        match rx.recv().unwrap() {
+            WindowEvent::FocusChanged(window) => {
+                assert_eq!(window.title.as_deref(), Some("vim main.rs"))
+            }
+            event => panic!("Unexpected event {:?}", event),
+        }
+    }
+}
@@ -0,0 +1,229 @@
+// Window icons as PNG:
+// - _NET_WM_ICON holds any number of icons, each one as width, height, then width * height
+//   pixels in non-premultiplied ARGB, one pixel per CARDINAL.
+// - Without it, the icon of the desktop entry is looked up in the icon themes of ~/.icons and
+//   <data dir>/icons, then /usr/share/pixmaps. The current theme (gtk-icon-theme-name of the
+//   GTK settings, or [Icons] Theme of kdeglobals) is searched first, then the themes it
+//   inherits from, then hicolor. The first theme with the icon gives its size closest to the
+//   requested one, from <theme>/<width>x<height>/apps/<name>.png or <theme>/apps/<size>/<name>.png
+//   (breeze). SVG icons are skipped, only PNG files are returned.
+#![cfg(target_os = "linux")]
+use {
+    super::desktop_entry::data_dirs,
+    anyhow::Result,
+    std::{
+        fs,
+        path::{Path, PathBuf},
+    },
+};
+
+/// Icon of `_NET_WM_ICON` data whose largest side is the closest to `size`, the larger one on a
+/// tie. Returns its width, height and pixels.
+pub fn pick_icon(data: &[u32], size: u32) -> Option<(u32, u32, &[u32])> {
+    let mut icons = Vec::new();
+    let mut rest = data;
+    while let [width, height, pixels @ ..] = rest {
+        let len = (*width as usize).checked_mul(*height as usize)?;
+        if len == 0 || pixels.len() < len {
+            break;
+        }
+        icons.push((*width, *height, &pixels[..len]));
+        rest = &pixels[len..];
+    }
+    icons.into_iter().min_by_key(|(width, height, _)| {
+        let side = *width.max(height);
+        (side.abs_diff(size), std::cmp::Reverse(side))
+    })
+}
+
+/// Encode ARGB pixels as an RGBA PNG.
+pub fn encode_png(width: u32, height: u32, argb: &[u32]) -> Result<Vec<u8>> {
+    let rgba: Vec<u8> = argb
+        .iter()
+        .flat_map(|pixel| {
+            let [a, r, g, b] = pixel.to_be_bytes();
+            [r, g, b, a]
+        })
+        .collect();
+    let mut png = Vec::new();
+    let mut encoder = png::Encoder::new(&mut png, width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(&rgba)?;
+    writer.finish()?;
+    Ok(png)
+}
+
+/// PNG file of an icon name from a desktop entry (or an absolute path to a PNG), read from
+/// the icon themes at the size closest to `size`.
+pub fn find_theme_icon(icon: &str, size: u32) -> Option<Vec<u8>> {
+    let path = if Path::new(icon).is_absolute() {
+        Some(PathBuf::from(icon)).filter(|path| path.extension().is_some_and(|ext| ext == "png"))
+    } else {
+        let mut icon_dirs: Vec<PathBuf> = dirs::home_dir()
+            .map(|home| home.join(".icons"))
+            .into_iter()
+            .collect();
+        icon_dirs.extend(data_dirs().into_iter().map(|dir| dir.join("icons")));
+        let themes = theme_chain(&icon_dirs, &current_theme().unwrap_or("hicolor".into()));
+        find_icon_file(&icon_dirs, &themes, icon, size).or_else(|| {
+            let pixmap = PathBuf::from(format!("/usr/share/pixmaps/{}.png", icon));
+            pixmap.is_file().then_some(pixmap)
+        })
+    };
+    fs::read(path?).ok()
+}
+
+/// Icon theme picked by the user in the GTK or Plasma settings.
+fn current_theme() -> Option<String> {
+    let config = dirs::config_dir()?;
+    let gtk = ["gtk-4.0", "gtk-3.0"].into_iter().find_map(|version| {
+        let settings = fs::read_to_string(config.join(version).join("settings.ini")).ok()?;
+        ini_value(&settings, "Settings", "gtk-icon-theme-name")
+    });
+    gtk.or_else(|| {
+        let kdeglobals = fs::read_to_string(config.join("kdeglobals")).ok()?;
+        ini_value(&kdeglobals, "Icons", "Theme")
+    })
+}
+
+/// `theme`, the themes it inherits from, depth first, then hicolor.
+fn theme_chain(icon_dirs: &[PathBuf], theme: &str) -> Vec<String> {
+    fn add(icon_dirs: &[PathBuf], theme: &str, chain: &mut Vec<String>) {
+        if chain.iter().any(|t| t == theme) {
+            return;
+        }
+        chain.push(theme.to_string());
+        let index = icon_dirs
+            .iter()
+            .find_map(|dir| fs::read_to_string(dir.join(theme).join("index.theme")).ok());
+        let inherits = index.and_then(|index| ini_value(&index, "Icon Theme", "Inherits"));
+        for parent in inherits.iter().flat_map(|list| list.split(',')) {
+            add(icon_dirs, parent.trim(), chain);
+        }
+    }
+
+    let mut chain = Vec::new();
+    add(icon_dirs, theme, &mut chain);
+    add(icon_dirs, "hicolor", &mut chain);
+    chain
+}
+
+/// PNG of `icon` in the first of `themes` that has it, at the size closest to `size`.
+fn find_icon_file(
+    icon_dirs: &[PathBuf],
+    themes: &[String],
+    icon: &str,
+    size: u32,
+) -> Option<PathBuf> {
+    let file = format!("{}.png", icon);
+    themes.iter().find_map(|theme| {
+        let mut found = Vec::new();
+        for theme_dir in icon_dirs.iter().map(|dir| dir.join(theme)) {
+            // <size>/apps/<icon>.png
+            for entry in fs::read_dir(&theme_dir).into_iter().flatten().flatten() {
+                if let Some(side) = parse_size_dir(&entry.file_name().to_string_lossy()) {
+                    found.push((side, entry.path().join("apps").join(&file)));
+                }
+            }
+            // apps/<size>/<icon>.png
+            let apps = theme_dir.join("apps");
+            for entry in fs::read_dir(&apps).into_iter().flatten().flatten() {
+                if let Some(side) = parse_size_dir(&entry.file_name().to_string_lossy()) {
+                    found.push((side, entry.path().join(&file)));
+                }
+            }
+        }
+        found
+            .into_iter()
+            .filter(|(_, path)| path.is_file())
+            .min_by_key(|(side, _)| (side.abs_diff(size), std::cmp::Reverse(*side)))
+            .map(|(_, path)| path)
+    })
+}
+
+/// "48x48" or "48" -> 48. Scaled directories ("48x48@2") and "scalable" have no fixed size.
+fn parse_size_dir(name: &str) -> Option<u32> {
+    let Some((width, height)) = name.split_once('x') else {
+        return name.parse().ok();
+    };
+    let width = width.parse().ok()?;
+    (height.parse::<u32>().ok()? == width).then_some(width)
+}
+
+/// Value of `key` in the `[section]` group of an INI file.
+fn ini_value(text: &str, section: &str, key: &str) -> Option<String> {
+    let mut in_section = false;
+    for line in text.lines().map(str::trim) {
+        if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            in_section = name == section;
+        } else if let Some((k, value)) = line.split_once('=').filter(|_| in_section) {
+            if k.trim() == key {
+                return Some(value.trim().trim_matches('"').to_string());
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+
+    #[test]
+    fn test_pick_icon() {
+        let mut data = vec![2, 2, 0xFF00_0000, 0xFF00_0000, 0xFF00_0000, 0xFF00_0000];
+        data.extend([4, 4]);
+        data.extend([0x80FF_0000; 16]);
+        // Truncated icon, ignored
+        data.extend([64, 64, 0]);
+
+        let (width, height, pixels) = pick_icon(&data, 3).unwrap();
+        assert_eq!((width, height, pixels.len()), (4, 4, 16));
+        assert_eq!(pick_icon(&data, 1).map(|(width, _, _)| width), Some(2));
+        assert!(pick_icon(&[], 32).is_none());
+
+        let png = encode_png(width, height, pixels).unwrap();
+        let decoder = png::Decoder::new(png.as_slice());
+        let mut reader = decoder.read_info().unwrap();
+        let mut buffer = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut buffer).unwrap();
+        assert_eq!((info.width, info.height), (4, 4));
+        assert_eq!(&buffer[..4], &[0xFF, 0, 0, 0x80]);
+    }
+
+    #[test]
+    fn test_find_icon_file() {
+        let dir = TempDir::new("icons");
+        for size in ["16x16", "48x48", "256x256", "scalable"] {
+            dir.write(&format!("hicolor/{}/apps/org.gnome.Nautilus.png", size), "");
+        }
+        // breeze layout, only a small icon, still preferred over the larger hicolor ones
+        dir.write("breeze/apps/16/org.gnome.Nautilus.png", "");
+        dir.write("breeze/apps/22@2x/org.gnome.Nautilus.png", "");
+        dir.write(
+            "breeze-dark/index.theme",
+            "[Icon Theme]\nName=Breeze Dark\nInherits=breeze,hicolor\n",
+        );
+
+        let root = dir.path();
+        let icon_dirs = vec![root.to_path_buf()];
+        let themes = theme_chain(&icon_dirs, "breeze-dark");
+        let found = find_icon_file(&icon_dirs, &themes, "org.gnome.Nautilus", 64);
+        let hicolor = find_icon_file(&icon_dirs, &["hicolor".into()], "org.gnome.Nautilus", 64);
+        let missing = find_icon_file(&icon_dirs, &themes, "missing", 64);
+
+        assert_eq!(themes, vec!["breeze-dark", "breeze", "hicolor"]);
+        assert_eq!(
+            found,
+            Some(root.join("breeze/apps/16/org.gnome.Nautilus.png"))
+        );
+        assert_eq!(
+            hicolor,
+            Some(root.join("hicolor/48x48/apps/org.gnome.Nautilus.png"))
+        );
+        assert_eq!(missing, None);
+    }
+}
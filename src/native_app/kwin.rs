@@ -0,0 +1,494 @@
+// KDE Plasma backend. KWin doesn't expose the active window over D-Bus, but it runs scripts that
+// can call D-Bus methods. The script in extensions/kwin is loaded through org.kde.KWin /Scripting,
+// with the unique bus name of our connection substituted, and reports every activation and caption
+// change of the active window as JSON to the `Report` method we export.
+// The script stays loaded while the session (or the subscription) lives and is unloaded on drop.
+#![cfg(target_os = "linux")]
+use {
+    super::{get_exec_path, WindowBackend},
+    crate::{
+        types::{Geometry, WindowState, Workspace},
+        WindowEvent, WindowInformation,
+    },
+    anyhow::{anyhow, Result},
+    serde::Deserialize,
+    std::fs::{self, DirBuilder, OpenOptions},
+    std::io::Write,
+    std::os::unix::fs::{DirBuilderExt, MetadataExt, OpenOptionsExt, PermissionsExt},
+    std::path::PathBuf,
+    std::sync::atomic::{AtomicU32, Ordering},
+    std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender, TryRecvError},
+    std::sync::Mutex,
+    std::thread,
+    std::time::{Duration, SystemTime},
+    zbus::blocking::Connection,
+};
+
+const KWIN_NAME: &str = "org.kde.KWin";
+const SCRIPTING_PATH: &str = "/Scripting";
+const SCRIPTING_IFACE: &str = "org.kde.kwin.Scripting";
+const SCRIPT_IFACE: &str = "org.kde.kwin.Script";
+const REPORT_PATH: &str = "/io/github/phucvinh57/ScreenInspector";
+
+const SCRIPT: &str = include_str!("../../extensions/kwin/screen-inspector.js");
+
+/// The script reports the active window as soon as it runs.
+const FIRST_REPORT_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Gives every loaded script its own plugin name, KWin refuses to load a name twice.
+static SCRIPT_COUNTER: AtomicU32 = AtomicU32::new(0);
+
+/// Report of the script. `None` when no window is active.
+type Report = Option<WindowEvent>;
+
+struct KWinSession {
+    _script: KWinScript,
+    reports: Receiver<Report>,
+    latest: Option<WindowInformation>,
+}
+
+/// Script shared by every call. Reset when the script stops reporting, e.g. after KWin restarted.
+static SESSION: Mutex<Option<KWinSession>> = Mutex::new(None);
+
+pub struct KWinBackend;
+
+impl WindowBackend for KWinBackend {
+    fn name(&self) -> &'static str {
+        "kwin"
+    }
+
+    fn current(&self) -> Result<Option<WindowInformation>> {
+        get_current_window_information()
+    }
+
+    fn subscribe(&self) -> Result<Receiver<WindowEvent>> {
+        subscribe()
+    }
+}
+
+/// Active window as last reported by the script.
+pub fn get_current_window_information() -> Result<Option<WindowInformation>> {
+    let mut session = SESSION
+        .lock()
+        .map_err(|_| anyhow!("KWin session poisoned"))?;
+    if session.is_none() {
+        let conn = Connection::session()?;
+        let (script, reports) = KWinScript::load(&conn)?;
+        let latest = match reports.recv_timeout(FIRST_REPORT_TIMEOUT) {
+            Ok(report) => report.map(|event| event.window().clone()),
+            Err(RecvTimeoutError::Timeout) => None,
+            Err(RecvTimeoutError::Disconnected) => return Err(anyhow!("KWin script stopped")),
+        };
+        *session = Some(KWinSession {
+            _script: script,
+            reports,
+            latest,
+        });
+    }
+
+    let Some(current) = session.as_mut() else {
+        return Ok(None);
+    };
+    loop {
+        match current.reports.try_recv() {
+            Ok(report) => current.latest = report.map(|event| event.window().clone()),
+            Err(TryRecvError::Empty) => return Ok(current.latest.clone()),
+            Err(TryRecvError::Disconnected) => {
+                *session = None;
+                return Err(anyhow!("KWin script stopped"));
+            }
+        }
+    }
+}
+
+/// Receive an event each time the active window or its caption changes.
+/// The script is unloaded once the receiver is dropped and the next event arrives.
+pub fn subscribe() -> Result<Receiver<WindowEvent>> {
+    let conn = Connection::session()?;
+    let (script, reports) = KWinScript::load(&conn)?;
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        let _script = script;
+        for event in reports.into_iter().flatten() {
+            if tx.send(event).is_err() {
+                // Receiver dropped
+                break;
+            }
+        }
+    });
+    Ok(rx)
+}
+
+/// Object the script calls back.
+struct Reporter {
+    tx: Sender<Report>,
+    /// Unique name of KWin, the only sender whose reports are trusted.
+    kwin: String,
+}
+
+#[zbus::interface(name = "io.github.phucvinh57.ScreenInspector.KWin")]
+impl Reporter {
+    fn report(
+        &self,
+        json: String,
+        #[zbus(header)] header: zbus::message::Header<'_>,
+    ) -> zbus::fdo::Result<()> {
+        if header.sender().map(|s| s.as_str()) != Some(self.kwin.as_str()) {
+            return Err(zbus::fdo::Error::AccessDenied(
+                "Reports are only accepted from KWin".into(),
+            ));
+        }
+        match parse_report(&json) {
+            Ok(report) => {
+                let _ = self.tx.send(report);
+            }
+            Err(e) => log::error!("Invalid report from the KWin script: {}", e),
+        }
+        Ok(())
+    }
+}
+
+/// Loaded script, unloaded when dropped.
+struct KWinScript {
+    conn: Connection,
+    plugin: String,
+    path: PathBuf,
+}
+
+impl KWinScript {
+    fn load(conn: &Connection) -> Result<(Self, Receiver<Report>)> {
+        let (tx, rx) = mpsc::channel();
+        let kwin: String = conn
+            .call_method(
+                Some("org.freedesktop.DBus"),
+                "/org/freedesktop/DBus",
+                Some("org.freedesktop.DBus"),
+                "GetNameOwner",
+                &(KWIN_NAME,),
+            )?
+            .body()
+            .deserialize()?;
+        if !conn
+            .object_server()
+            .at(REPORT_PATH, Reporter { tx, kwin })?
+        {
+            return Err(anyhow!("A KWin script already reports to this connection"));
+        }
+        let service = conn
+            .unique_name()
+            .ok_or(anyhow!("Connection has no unique name"))?
+            .to_string();
+
+        let plugin = format!(
+            "screen-inspector-{}-{}",
+            std::process::id(),
+            SCRIPT_COUNTER.fetch_add(1, Ordering::Relaxed)
+        );
+        let path = script_dir()?.join(format!("{}.js", plugin));
+        // Another user could have put a script there, never load a file we didn't create
+        OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .mode(0o600)
+            .open(&path)?
+            .write_all(SCRIPT.replace("%SERVICE%", &service).as_bytes())?;
+        let script = KWinScript {
+            conn: conn.clone(),
+            plugin,
+            path,
+        };
+
+        let reply = conn.call_method(
+            Some(KWIN_NAME),
+            SCRIPTING_PATH,
+            Some(SCRIPTING_IFACE),
+            "loadScript",
+            &(script.path.display().to_string(), &script.plugin),
+        )?;
+        let id: i32 = reply.body().deserialize()?;
+        if id < 0 {
+            return Err(anyhow!("KWin refused to load {}", script.path.display()));
+        }
+
+        // Plasma 5.21+ and 6 put scripts under /Scripting, older versions at the root
+        let run = |path: String| {
+            conn.call_method(
+                Some(KWIN_NAME),
+                path.as_str(),
+                Some(SCRIPT_IFACE),
+                "run",
+                &(),
+            )
+        };
+        run(format!("/Scripting/Script{}", id)).or_else(|_| run(format!("/{}", id)))?;
+        Ok((script, rx))
+    }
+}
+
+/// Directory only we can write to: $XDG_RUNTIME_DIR, or a 0700 directory of ours in /tmp.
+fn script_dir() -> Result<PathBuf> {
+    if let Some(dir) = std::env::var_os("XDG_RUNTIME_DIR") {
+        return Ok(PathBuf::from(dir));
+    }
+    let uid = fs::metadata("/proc/self")?.uid();
+    let dir = std::env::temp_dir().join(format!("screen-inspector-{}", uid));
+    match DirBuilder::new().mode(0o700).create(&dir) {
+        Ok(()) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {}
+        Err(e) => return Err(e.into()),
+    }
+    let metadata = fs::symlink_metadata(&dir)?;
+    if !metadata.is_dir() || metadata.uid() != uid || metadata.permissions().mode() & 0o077 != 0 {
+        return Err(anyhow!("{} isn't a private directory", dir.display()));
+    }
+    Ok(dir)
+}
+
+impl Drop for KWinScript {
+    fn drop(&mut self) {
+        let _ = self.conn.call_method(
+            Some(KWIN_NAME),
+            SCRIPTING_PATH,
+            Some(SCRIPTING_IFACE),
+            "unloadScript",
+            &(&self.plugin,),
+        );
+        let _ = self.conn.object_server().remove::<Reporter, _>(REPORT_PATH);
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ScriptReport {
+    event: String,
+    window: Option<ReportedWindow>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ReportedWindow {
+    caption: String,
+    resource_class: String,
+    resource_name: String,
+    pid: Option<i64>,
+    desktop_index: Option<i64>,
+    desktop_name: Option<String>,
+    activity: Option<String>,
+    x: Option<f64>,
+    y: Option<f64>,
+    width: Option<f64>,
+    height: Option<f64>,
+    #[serde(default)]
+    full_screen: bool,
+    #[serde(default)]
+    minimized: bool,
+    #[serde(default)]
+    keep_above: bool,
+    #[serde(default)]
+    demands_attention: bool,
+}
+
+fn parse_report(json: &str) -> Result<Report> {
+    let report: ScriptReport = serde_json::from_str(json)?;
+    let Some(window) = report.window else {
+        return Ok(None);
+    };
+    let window = window_from_report(window);
+    match report.event.as_str() {
+        "activated" => Ok(Some(WindowEvent::FocusChanged(window))),
+        "caption" => Ok(Some(WindowEvent::TitleChanged(window))),
+        event => Err(anyhow!("Unknown event {}", event)),
+    }
+}
+
+fn window_from_report(window: ReportedWindow) -> WindowInformation {
+    let time = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+
+    // resourceName and resourceClass are the WM_CLASS instance and class
+    let class = [window.resource_name, window.resource_class]
+        .into_iter()
+        .filter(|s| !s.is_empty())
+        .collect();
+
+    // KWin reports 0 for windows whose client didn't tell its PID
+    let pid = window
+        .pid
+        .and_then(|pid| u32::try_from(pid).ok())
+        .filter(|pid| *pid > 0);
+    let execpath = pid.and_then(get_exec_path);
+
+    let workspace =
+        (window.desktop_index.is_some() || window.desktop_name.is_some()).then_some(Workspace {
+            index: window.desktop_index.and_then(|i| u32::try_from(i).ok()),
+            name: window.desktop_name,
+        });
+
+    let geometry = match (window.x, window.y, window.width, window.height) {
+        (Some(x), Some(y), Some(width), Some(height)) => Some(Geometry {
+            x: x as i32,
+            y: y as i32,
+            width: width as u32,
+            height: height as u32,
+        }),
+        _ => None,
+    };
+
+    WindowInformation {
+        time,
+        title: Some(window.caption),
+        class,
+        execpath,
+        pid,
+        workspace,
+        geometry,
+        state: Some(WindowState {
+            fullscreen: window.full_screen,
+            hidden: window.minimized,
+            above: window.keep_above,
+            demands_attention: window.demands_attention,
+            ..Default::default()
+        }),
+        activity: window.activity.filter(|a| !a.is_empty()),
+        ..Default::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::start_dbus_daemon;
+    use std::sync::Arc;
+
+    #[derive(Default)]
+    struct StubState {
+        service: Option<String>,
+        unloaded: Vec<String>,
+    }
+
+    struct StubScripting(Arc<Mutex<StubState>>);
+
+    #[zbus::interface(name = "org.kde.kwin.Scripting")]
+    impl StubScripting {
+        #[zbus(name = "loadScript")]
+        fn load_script(&self, path: String, _plugin: String) -> i32 {
+            let script = fs::read_to_string(path).unwrap();
+            let service = script
+                .lines()
+                .find_map(|l| l.strip_prefix("const SERVICE = \""))
+                .and_then(|l| l.strip_suffix("\";"))
+                .map(str::to_string);
+            self.0.lock().unwrap().service = service;
+            7
+        }
+
+        #[zbus(name = "unloadScript")]
+        fn unload_script(&self, plugin: String) -> bool {
+            self.0.lock().unwrap().unloaded.push(plugin);
+            true
+        }
+    }
+
+    /// Runs the script by replaying what it sends for the active window.
+    struct StubScript(Arc<Mutex<StubState>>, Connection);
+
+    #[zbus::interface(name = "org.kde.kwin.Script")]
+    impl StubScript {
+        #[zbus(name = "run")]
+        fn run(&self) {
+            let service = self.0.lock().unwrap().service.clone().unwrap();
+            let conn = self.1.clone();
+            thread::spawn(move || {
+                let report = |json: &str| {
+                    conn.call_method(
+                        Some(service.as_str()),
+                        REPORT_PATH,
+                        Some("io.github.phucvinh57.ScreenInspector.KWin"),
+                        "Report",
+                        &(json,),
+                    )
+                    .unwrap();
+                };
+                report(
+                    r#"{"event": "activated", "window": {"caption": "Dolphin", "resourceClass": "org.kde.dolphin",
+                        "resourceName": "dolphin", "pid": 1, "desktopIndex": 1, "desktopName": "Desktop 2",
+                        "activity": "9f0b0f4e-4c38-4c8e-9d8a-2f8a1c0e7b11", "x": 0, "y": 0,
+                        "width": 1280, "height": 800, "fullScreen": false, "minimized": false,
+                        "keepAbove": true, "demandsAttention": false}}"#,
+                );
+                report(r#"{"event": "activated", "window": null}"#);
+            });
+        }
+    }
+
+    #[test]
+    fn test_script_reports() {
+        let Some((_daemon, address)) = start_dbus_daemon() else {
+            eprintln!("dbus-daemon not available, skipping");
+            return;
+        };
+        let state = Arc::new(Mutex::new(StubState::default()));
+        let kwin = zbus::blocking::connection::Builder::address(address.as_str())
+            .unwrap()
+            .name(KWIN_NAME)
+            .unwrap()
+            .serve_at(SCRIPTING_PATH, StubScripting(state.clone()))
+            .unwrap()
+            .build()
+            .unwrap();
+        kwin.object_server()
+            .at(
+                "/Scripting/Script7",
+                StubScript(state.clone(), kwin.clone()),
+            )
+            .unwrap();
+
+        let client = zbus::blocking::connection::Builder::address(address.as_str())
+            .unwrap()
+            .build()
+            .unwrap();
+        let (script, reports) = KWinScript::load(&client).unwrap();
+        let plugin = script.plugin.clone();
+
+        let report = reports.recv_timeout(Duration::from_secs(5)).unwrap();
+        let Some(WindowEvent::FocusChanged(window)) = report else {
+            panic!("Unexpected report {:?}", report);
+        };
+        assert_eq!(window.title.as_deref(), Some("Dolphin"));
+        assert_eq!(window.class, vec!["dolphin", "org.kde.dolphin"]);
+        assert_eq!(window.pid, Some(1));
+        assert_eq!(window.workspace.unwrap().name.as_deref(), Some("Desktop 2"));
+        assert_eq!(window.geometry.unwrap().width, 1280);
+        assert!(window.state.unwrap().above);
+        assert_eq!(
+            window.activity.as_deref(),
+            Some("9f0b0f4e-4c38-4c8e-9d8a-2f8a1c0e7b11")
+        );
+
+        // Focus on the desktop
+        assert!(reports
+            .recv_timeout(Duration::from_secs(5))
+            .unwrap()
+            .is_none());
+
+        // Any other client of the bus is refused
+        let spoofer = zbus::blocking::connection::Builder::address(address.as_str())
+            .unwrap()
+            .build()
+            .unwrap();
+        let spoofed = spoofer.call_method(
+            Some(client.unique_name().unwrap().as_str()),
+            REPORT_PATH,
+            Some("io.github.phucvinh57.ScreenInspector.KWin"),
+            "Report",
+            &(r#"{"event": "activated", "window": null}"#,),
+        );
+        assert!(spoofed.is_err());
+        assert!(reports.try_recv().is_err());
+
+        drop(script);
+        assert_eq!(state.lock().unwrap().unloaded, vec![plugin]);
+    }
+}
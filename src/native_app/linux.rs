@@ -1,113 +1,725 @@
+// X11 backend. Keeps one connection to the X server for the whole process and
+// reads the EWMH/ICCCM properties of the active window directly:
+// - _NET_ACTIVE_WINDOW on the root window: id of the focused window
+// - _NET_WM_NAME (UTF8_STRING), falling back to WM_NAME: window title
+// - WM_CLASS: two NUL-terminated strings, instance then class
+// - _NET_WM_PID: PID, from which the exec path is read with readlink /proc/<pid>/exe
+// - _NET_WM_STATE, _NET_WM_WINDOW_TYPE, WM_WINDOW_ROLE, _GTK_APPLICATION_ID
+// - _NET_WM_DESKTOP: desktop index, named from _NET_DESKTOP_NAMES on the root window
+// - WM_CLIENT_MACHINE: host of the client, compared to ours to flag remote clients
+// - absolute geometry: size from GetGeometry, position translated to root coordinates
+// - _NET_WM_ICON, on request: the icons of the window, converted to PNG
+//
+// `list_windows` walks _NET_CLIENT_LIST_STACKING on the root window, the managed windows from
+// bottom to top. A window is visible when it is mapped (minimized windows are unmapped), not
+// hidden and on the current desktop (_NET_CURRENT_DESKTOP) or sticky. It may still be covered
+// by the windows above it, which the z-order and geometry tell.
+//
+// `subscribe` opens a second connection that selects PropertyChangeMask on the root window
+// (for _NET_ACTIVE_WINDOW) and on the active window (for its title), so the caller is woken up
+// only when focus or title change.
 #[cfg(target_os = "linux")]
 use {
-    crate::WindowInformation,
-    anyhow::{anyhow, Ok, Result},
-    regex::Regex,
+    super::{get_exec_path, icon, WindowBackend},
+    crate::{
+        types::{Geometry, WindowState, WindowType, Workspace},
+        InspectError, WindowEvent, WindowInformation,
+    },
+    anyhow::{anyhow, Result},
     std::fs,
-    std::process::Command,
+    std::sync::mpsc::{self, Receiver, Sender},
+    std::sync::Mutex,
+    std::thread,
     std::time::SystemTime,
+    x11rb::connection::Connection,
+    x11rb::errors::{ConnectError, ReplyError},
+    x11rb::protocol::xproto::{
+        AtomEnum, ChangeWindowAttributesAux, ConnectionExt, EventMask, MapState, Window,
+    },
+    x11rb::protocol::{ErrorKind, Event},
+    x11rb::rust_connection::RustConnection,
 };
 
 #[cfg(target_os = "linux")]
-pub fn get_current_window_information() -> Option<WindowInformation> {
-    let window_raw_id = get_window_id().unwrap();
-    if window_raw_id == 0 {
-        // No open window found
-        return None;
+x11rb::atom_manager! {
+    pub Atoms: AtomsCookie {
+        _NET_ACTIVE_WINDOW,
+        _NET_CLIENT_LIST_STACKING,
+        _NET_CURRENT_DESKTOP,
+        _NET_DESKTOP_NAMES,
+        _NET_WM_NAME,
+        _NET_WM_PID,
+        _NET_WM_DESKTOP,
+        _NET_WM_STATE,
+        _NET_WM_STATE_FULLSCREEN,
+        _NET_WM_STATE_MAXIMIZED_VERT,
+        _NET_WM_STATE_MAXIMIZED_HORZ,
+        _NET_WM_STATE_HIDDEN,
+        _NET_WM_STATE_ABOVE,
+        _NET_WM_STATE_DEMANDS_ATTENTION,
+        _NET_WM_WINDOW_TYPE,
+        _NET_WM_ICON,
+        _GTK_APPLICATION_ID,
+        WM_WINDOW_ROLE,
+        UTF8_STRING,
     }
+}
 
-    get_window_information_by_id(window_raw_id)
+#[cfg(target_os = "linux")]
+struct X11Session {
+    conn: RustConnection,
+    root: Window,
+    atoms: Atoms,
 }
 
+/// Connection shared by every call. Reset when a request fails, e.g. after the X server restarted.
+#[cfg(target_os = "linux")]
+static SESSION: Mutex<Option<X11Session>> = Mutex::new(None);
+
 #[cfg(target_os = "linux")]
-fn get_window_id() -> Result<i64> {
-    let bin = "xprop";
-    let args = ["-root", "_NET_ACTIVE_WINDOW"];
-    let window_id_regex = Regex::new(r"0x[a-fA-F0-9]+").unwrap();
-    let window_raw_id = Command::new(bin)
-        .args(&args)
-        .output()
-        .expect("Failed to execute command");
+pub struct X11Backend;
 
-    if !window_raw_id.status.success() {
-        let stderr = String::from_utf8_lossy(&window_raw_id.stderr);
-        return Err(anyhow!("Get window ID error: {}", stderr));
+#[cfg(target_os = "linux")]
+impl WindowBackend for X11Backend {
+    fn name(&self) -> &'static str {
+        "x11"
     }
 
-    let stdout = String::from_utf8_lossy(&window_raw_id.stdout);
-    if let Some(captured) = window_id_regex.find(&stdout) {
-        let window_id_hex = captured.as_str().trim_start_matches("0x");
-        let window_id = i64::from_str_radix(window_id_hex, 16).unwrap();
-        return Ok(window_id);
+    fn current(&self) -> Result<Option<WindowInformation>> {
+        get_current_window_information()
     }
-    Err(anyhow!("No window ID found in the input string."))
-}
 
-#[cfg(target_os = "linux")]
-fn get_window_information_by_id(window_id: i64) -> Option<WindowInformation> {
-    let bin = "xprop";
-    let window_raw_infor = Command::new(bin)
-        .env("LC_ALL", "C.utf8")
-        .arg("-id")
-        .arg(window_id.to_string())
-        .arg("-notype")
-        .arg("WM_NAME")
-        .arg("WM_CLASS")
-        .arg("_NET_WM_NAME")
-        .arg("_NET_WM_PID")
-        .output()
-        .expect("Failed to execute command");
+    fn subscribe(&self) -> Result<Receiver<WindowEvent>> {
+        subscribe()
+    }
 
-    if !window_raw_infor.status.success() {
-        let stderr = String::from_utf8_lossy(&window_raw_infor.stderr);
-        log::error!("Get window information error: {}", stderr);
-        return None;
+    fn list_windows(&self) -> Result<Vec<WindowInformation>> {
+        with_session(X11Session::list_windows)
     }
 
-    let stdout = String::from_utf8_lossy(&window_raw_infor.stdout);
-    let unix_ts = SystemTime::now()
-        .duration_since(SystemTime::UNIX_EPOCH)
-        .unwrap();
+    fn icon(&self, window: &WindowInformation, size: u32) -> Result<Option<Vec<u8>>> {
+        match window.id {
+            Some(id) => get_window_icon(id.try_into()?, size),
+            None => Ok(None),
+        }
+    }
+}
 
-    let mut title: Option<String> = None;
-    let time = unix_ts.as_secs();
-    let mut class: Option<Vec<String>> = None;
-    let mut exec_path: Option<String> = None;
+#[cfg(target_os = "linux")]
+pub fn get_current_window_information() -> Result<Option<WindowInformation>> {
+    with_session(X11Session::get_active_window_information).map_err(|e| {
+        if is_bad_window(&e) {
+            InspectError::NoFocusedWindow.into()
+        } else {
+            e
+        }
+    })
+}
 
-    for line in stdout.lines() {
-        let parts: Vec<&str> = line.split('=').map(|s| s.trim()).collect();
-        if parts.len() < 2 {
-            continue;
+/// `_NET_WM_ICON` of a window as PNG, at the size closest to `size`.
+/// `None` when the window has no icon or was closed.
+#[cfg(target_os = "linux")]
+pub fn get_window_icon(window: Window, size: u32) -> Result<Option<Vec<u8>>> {
+    let icon = |session: &X11Session| {
+        session.get_list(window, session.atoms._NET_WM_ICON, AtomEnum::CARDINAL)
+    };
+    let data = match with_session(icon) {
+        Ok(data) => data,
+        Err(e) if is_bad_window(&e) => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    data.as_deref()
+        .and_then(|data| icon::pick_icon(data, size))
+        .map(|(width, height, pixels)| icon::encode_png(width, height, pixels))
+        .transpose()
+}
+
+/// Run `f` on the shared session, connecting first if needed. The session is reset when `f`
+/// fails, unless only the window was destroyed between two requests.
+#[cfg(target_os = "linux")]
+fn with_session<T>(f: impl FnOnce(&X11Session) -> Result<T>) -> Result<T> {
+    let mut session = SESSION
+        .lock()
+        .map_err(|_| anyhow!("X11 session poisoned"))?;
+    let current = match session.take() {
+        Some(current) => current,
+        None => X11Session::connect()?,
+    };
+    let result = f(&current);
+    let keep = match &result {
+        Ok(_) => true,
+        Err(e) => is_bad_window(e),
+    };
+    if keep {
+        *session = Some(current);
+    }
+    result
+}
+
+/// Receive an event each time the focused window or its title changes.
+/// The watching thread stops once the receiver is dropped and the next event arrives.
+#[cfg(target_os = "linux")]
+pub fn subscribe() -> Result<Receiver<WindowEvent>> {
+    let session = X11Session::connect()?;
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        if let Err(e) = session.watch(tx) {
+            log::error!("X11 event loop stopped: {}", e);
         }
+    });
+    Ok(rx)
+}
 
-        match parts[0] {
-            "WM_NAME" => title = Some(parts[1].trim_matches('"').to_string()),
-            "WM_CLASS" => {
-                let wm_class = parts[1].split(',').map(|s| s.trim().trim_matches('"'));
-                class = Some(wm_class.map(|s| s.to_string()).collect());
+#[cfg(target_os = "linux")]
+impl X11Session {
+    fn connect() -> Result<Self> {
+        let (conn, screen_num) = x11rb::connect(None).map_err(|e| match e {
+            ConnectError::SetupAuthenticate(_) | ConnectError::SetupFailed(_) => {
+                InspectError::PermissionDenied(format!("X server refused the connection: {}", e))
+            }
+            e => {
+                log::debug!("Cannot connect to X server: {}", e);
+                InspectError::NoDisplay
             }
-            "_NET_WM_PID" => {
-                let pid = parts[1].trim().parse::<i32>().unwrap();
-                if let std::result::Result::Ok(path) = fs::read_link(format!("/proc/{}/exe", pid)) {
-                    let path_str = path.as_path().display().to_string();
-                    exec_path = Some(path_str);
-                }
+        })?;
+        let root = conn.setup().roots[screen_num].root;
+        let atoms = Atoms::new(&conn)?.reply()?;
+        Ok(X11Session { conn, root, atoms })
+    }
+
+    fn watch(&self, tx: Sender<WindowEvent>) -> Result<()> {
+        self.select_property_changes(self.root, true)?;
+        let mut active = self.get_active_window()?;
+        if active != 0 {
+            match self.select_property_changes(active, true) {
+                Ok(()) => {}
+                Err(e) if is_bad_window(&e) => active = 0,
+                Err(e) => return Err(e),
             }
-            "_NET_WM_NAME" => {
-                if title.is_none() {
-                    title = Some(parts[1].trim_matches('"').to_string());
-                }
+        }
+        let mut last_title: Option<String> = None;
+
+        loop {
+            let Event::PropertyNotify(event) = self.conn.wait_for_event()? else {
+                // Errors for windows destroyed before we unselected them end up here too
+                continue;
+            };
+
+            let window_event =
+                if event.window == self.root && event.atom == self.atoms._NET_ACTIVE_WINDOW {
+                    let window_id = self.get_active_window()?;
+                    if window_id == active {
+                        continue;
+                    }
+                    if active != 0 {
+                        self.select_property_changes(active, false)?;
+                    }
+                    active = window_id;
+                    if active == 0 {
+                        last_title = None;
+                        continue;
+                    }
+                    let window = match self
+                        .select_property_changes(active, true)
+                        .and_then(|()| self.get_window_information_by_id(active))
+                    {
+                        Ok(window) => window,
+                        // Closed right after taking the focus, wait for the next active window
+                        Err(e) if is_bad_window(&e) => {
+                            active = 0;
+                            last_title = None;
+                            continue;
+                        }
+                        Err(e) => return Err(e),
+                    };
+                    last_title = window.title.clone();
+                    WindowEvent::FocusChanged(window)
+                } else if event.window == active
+                    && (event.atom == self.atoms._NET_WM_NAME
+                        || event.atom == u32::from(AtomEnum::WM_NAME))
+                {
+                    let window = match self.get_window_information_by_id(active) {
+                        Ok(window) => window,
+                        Err(e) if is_bad_window(&e) => {
+                            active = 0;
+                            last_title = None;
+                            continue;
+                        }
+                        Err(e) => return Err(e),
+                    };
+                    // Clients usually update _NET_WM_NAME and WM_NAME together
+                    if last_title == window.title {
+                        continue;
+                    }
+                    last_title = window.title.clone();
+                    WindowEvent::TitleChanged(window)
+                } else {
+                    continue;
+                };
+
+            if tx.send(window_event).is_err() {
+                // Receiver dropped
+                return Ok(());
             }
-            _ => {}
         }
     }
 
-    let window = WindowInformation {
-        time,
-        title: title.unwrap(),
-        class: class.unwrap(),
-        execpath: exec_path.unwrap(),
-        url: None,
-    };
-    Some(window)
+    fn select_property_changes(&self, window: Window, enabled: bool) -> Result<()> {
+        let mask = if enabled {
+            EventMask::PROPERTY_CHANGE
+        } else {
+            EventMask::NO_EVENT
+        };
+        self.conn
+            .change_window_attributes(window, &ChangeWindowAttributesAux::new().event_mask(mask))?;
+        self.conn.flush()?;
+        Ok(())
+    }
+
+    fn get_active_window_information(&self) -> Result<Option<WindowInformation>> {
+        let window_id = self.get_active_window()?;
+        if window_id == 0 {
+            // No open window found
+            return Ok(None);
+        }
+        self.get_window_information_by_id(window_id).map(Some)
+    }
+
+    /// Managed windows from the bottom to the top of the stack.
+    fn list_windows(&self) -> Result<Vec<WindowInformation>> {
+        let stacking = self
+            .get_list(
+                self.root,
+                self.atoms._NET_CLIENT_LIST_STACKING,
+                AtomEnum::WINDOW,
+            )?
+            .ok_or(anyhow!(
+                "_NET_CLIENT_LIST_STACKING is not set on the root window"
+            ))?;
+        let current_desktop = self.get_cardinal(self.root, self.atoms._NET_CURRENT_DESKTOP)?;
+
+        let mut windows = Vec::with_capacity(stacking.len());
+        for (z_order, window_id) in stacking.into_iter().enumerate() {
+            let result = self
+                .get_window_information_by_id(window_id)
+                .and_then(|window| {
+                    let attributes = self.conn.get_window_attributes(window_id)?.reply()?;
+                    Ok((window, attributes.map_state == MapState::VIEWABLE))
+                });
+            let (mut window, mapped) = match result {
+                Ok(window) => window,
+                // Closed while the list was read
+                Err(e) if is_bad_window(&e) => continue,
+                Err(e) => return Err(e),
+            };
+            let desktop = window.workspace.as_ref().and_then(|w| w.index);
+            window.z_order = Some(z_order as u32);
+            window.visible = Some(is_visible(mapped, window.state, desktop, current_desktop));
+            windows.push(window);
+        }
+        Ok(windows)
+    }
+
+    fn get_active_window(&self) -> Result<Window> {
+        let reply = self
+            .conn
+            .get_property(
+                false,
+                self.root,
+                self.atoms._NET_ACTIVE_WINDOW,
+                AtomEnum::WINDOW,
+                0,
+                1,
+            )?
+            .reply()?;
+        reply
+            .value32()
+            .and_then(|mut v| v.next())
+            .ok_or(anyhow!("_NET_ACTIVE_WINDOW is not set on the root window"))
+    }
+
+    fn get_window_information_by_id(&self, window_id: Window) -> Result<WindowInformation> {
+        let time = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)?
+            .as_secs();
+
+        let net_wm_name =
+            self.get_text(window_id, self.atoms._NET_WM_NAME, self.atoms.UTF8_STRING)?;
+        let title = match net_wm_name {
+            Some(name) => Some(name),
+            None => self.get_text(window_id, AtomEnum::WM_NAME.into(), AtomEnum::ANY.into())?,
+        };
+
+        let class = self
+            .get_property(
+                window_id,
+                AtomEnum::WM_CLASS.into(),
+                AtomEnum::STRING.into(),
+            )?
+            .map(|raw| parse_wm_class(&raw))
+            .unwrap_or_default();
+
+        let state = self
+            .get_list(window_id, self.atoms._NET_WM_STATE, AtomEnum::ATOM)?
+            .map(|states| self.parse_wm_state(&states));
+        let workspace = self.get_workspace(window_id)?;
+        let window_type = match self
+            .get_list(window_id, self.atoms._NET_WM_WINDOW_TYPE, AtomEnum::ATOM)?
+            .and_then(|types| types.first().copied())
+        {
+            // Listed by preference, the first one is the one the client means
+            Some(atom) => Some(parse_window_type(&self.get_atom_name(atom)?)),
+            None => None,
+        };
+        let role = self.get_text(
+            window_id,
+            self.atoms.WM_WINDOW_ROLE,
+            AtomEnum::STRING.into(),
+        )?;
+        let application_id = self.get_text(
+            window_id,
+            self.atoms._GTK_APPLICATION_ID,
+            self.atoms.UTF8_STRING,
+        )?;
+        let client_machine = self.get_text(
+            window_id,
+            AtomEnum::WM_CLIENT_MACHINE.into(),
+            AtomEnum::STRING.into(),
+        )?;
+        let hostname = fs::read_to_string("/proc/sys/kernel/hostname").ok();
+        let remote = remote_client(client_machine.as_deref(), hostname.as_deref());
+        let pid = self.get_cardinal(window_id, self.atoms._NET_WM_PID)?;
+        let pid = local_pid(pid, remote);
+        let execpath = pid.and_then(get_exec_path);
+
+        Ok(WindowInformation {
+            time,
+            title,
+            class,
+            execpath,
+            pid,
+            id: Some(window_id as u64),
+            workspace,
+            geometry: Some(self.get_geometry(window_id)?),
+            state,
+            window_type,
+            role,
+            application_id,
+            client_machine,
+            remote,
+            ..Default::default()
+        })
+    }
+
+    /// Desktop of the window, with its name from _NET_DESKTOP_NAMES. Sticky windows are shown
+    /// on every desktop, they get the current one.
+    fn get_workspace(&self, window: Window) -> Result<Option<Workspace>> {
+        let index = match self.get_cardinal(window, self.atoms._NET_WM_DESKTOP)? {
+            Some(u32::MAX) => self.get_cardinal(self.root, self.atoms._NET_CURRENT_DESKTOP)?,
+            desktop => desktop,
+        };
+        let Some(index) = index else {
+            return Ok(None);
+        };
+        let names = self
+            .get_property(
+                self.root,
+                self.atoms._NET_DESKTOP_NAMES,
+                self.atoms.UTF8_STRING,
+            )?
+            .map(|names| parse_desktop_names(&names))
+            .unwrap_or_default();
+        Ok(Some(Workspace {
+            index: Some(index),
+            name: names
+                .into_iter()
+                .nth(index as usize)
+                .filter(|name| !name.is_empty()),
+        }))
+    }
+
+    fn parse_wm_state(&self, states: &[u32]) -> WindowState {
+        let has = |atom| states.contains(&atom);
+        WindowState {
+            fullscreen: has(self.atoms._NET_WM_STATE_FULLSCREEN),
+            maximized: has(self.atoms._NET_WM_STATE_MAXIMIZED_VERT)
+                && has(self.atoms._NET_WM_STATE_MAXIMIZED_HORZ),
+            hidden: has(self.atoms._NET_WM_STATE_HIDDEN),
+            above: has(self.atoms._NET_WM_STATE_ABOVE),
+            demands_attention: has(self.atoms._NET_WM_STATE_DEMANDS_ATTENTION),
+        }
+    }
+
+    /// Position of the client area in root coordinates. The window itself is positioned
+    /// relative to the frame the window manager reparented it into.
+    fn get_geometry(&self, window: Window) -> Result<Geometry> {
+        let geometry = self.conn.get_geometry(window)?.reply()?;
+        let position = self
+            .conn
+            .translate_coordinates(window, self.root, 0, 0)?
+            .reply()?;
+        Ok(Geometry {
+            x: position.dst_x.into(),
+            y: position.dst_y.into(),
+            width: geometry.width.into(),
+            height: geometry.height.into(),
+        })
+    }
+
+    fn get_atom_name(&self, atom: u32) -> Result<String> {
+        let reply = self.conn.get_atom_name(atom)?.reply()?;
+        Ok(String::from_utf8_lossy(&reply.name).to_string())
+    }
+
+    /// Value of a property holding a list of 32-bit values (ATOM[], WINDOW[], CARDINAL[]),
+    /// `None` if the window doesn't have it.
+    fn get_list(&self, window: Window, property: u32, kind: AtomEnum) -> Result<Option<Vec<u32>>> {
+        let reply = self
+            .conn
+            .get_property(false, window, property, kind, 0, u32::MAX / 4)?
+            .reply()?;
+        Ok(reply.value32().map(|values| values.collect()))
+    }
+
+    /// Raw value of a property, `None` if the window doesn't have it or it isn't of type `kind`.
+    fn get_property(&self, window: Window, property: u32, kind: u32) -> Result<Option<Vec<u8>>> {
+        let reply = self
+            .conn
+            .get_property(false, window, property, kind, 0, u32::MAX / 4)?
+            .reply()?;
+        if !has_type(kind, reply.type_) {
+            return Ok(None);
+        }
+        Ok(Some(reply.value))
+    }
+
+    /// Text property, `None` if the window doesn't have it or it isn't of type `kind`.
+    fn get_text(&self, window: Window, property: u32, kind: u32) -> Result<Option<String>> {
+        let reply = self
+            .conn
+            .get_property(false, window, property, kind, 0, u32::MAX / 4)?
+            .reply()?;
+        Ok(decode_property(kind, reply.type_, &reply.value))
+    }
+
+    fn get_cardinal(&self, window: Window, property: u32) -> Result<Option<u32>> {
+        let reply = self
+            .conn
+            .get_property(false, window, property, AtomEnum::CARDINAL, 0, 1)?
+            .reply()?;
+        Ok(reply.value32().and_then(|mut v| v.next()))
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn is_bad_window(error: &anyhow::Error) -> bool {
+    matches!(
+        error.downcast_ref::<ReplyError>(),
+        Some(ReplyError::X11Error(e)) if e.error_kind == ErrorKind::Window
+    )
+}
+
+/// `desktop` is `None` when the window manager doesn't tell.
+#[cfg(target_os = "linux")]
+fn is_visible(
+    mapped: bool,
+    state: Option<WindowState>,
+    desktop: Option<u32>,
+    current_desktop: Option<u32>,
+) -> bool {
+    let hidden = state.is_some_and(|state| state.hidden);
+    let on_current_desktop =
+        desktop.is_none() || current_desktop.is_none() || desktop == current_desktop;
+    mapped && !hidden && on_current_desktop
+}
+
+#[cfg(target_os = "linux")]
+fn parse_window_type(atom_name: &str) -> WindowType {
+    match atom_name.strip_prefix("_NET_WM_WINDOW_TYPE_") {
+        Some("NORMAL") => WindowType::Normal,
+        Some("DIALOG") => WindowType::Dialog,
+        Some("UTILITY") => WindowType::Utility,
+        Some("TOOLBAR") => WindowType::Toolbar,
+        Some("MENU" | "DROPDOWN_MENU" | "POPUP_MENU") => WindowType::Menu,
+        Some("SPLASH") => WindowType::Splash,
+        Some("DOCK") => WindowType::Dock,
+        Some("DESKTOP") => WindowType::Desktop,
+        Some("NOTIFICATION") => WindowType::Notification,
+        _ => WindowType::Other(atom_name.to_string()),
+    }
+}
+
+/// Whether a GetProperty reply for `kind` holds a value. When the property has another type,
+/// the server replies with that type and an empty value.
+#[cfg(target_os = "linux")]
+fn has_type(kind: u32, type_: u32) -> bool {
+    type_ != u32::from(AtomEnum::NONE) && (kind == u32::from(AtomEnum::ANY) || type_ == kind)
+}
+
+/// Text of a GetProperty reply for `kind`. Decoded after the type the client stored: Latin-1
+/// for STRING, UTF-8 for UTF8_STRING (and, lossily, COMPOUND_TEXT).
+#[cfg(target_os = "linux")]
+fn decode_property(kind: u32, type_: u32, value: &[u8]) -> Option<String> {
+    if !has_type(kind, type_) {
+        return None;
+    }
+    if type_ == u32::from(AtomEnum::STRING) {
+        return Some(decode_latin1(value));
+    }
+    Some(decode_text(value))
+}
+
+/// `None` when either host is unknown.
+#[cfg(target_os = "linux")]
+fn remote_client(client_machine: Option<&str>, hostname: Option<&str>) -> Option<bool> {
+    Some(is_remote_host(client_machine?, hostname?.trim()))
+}
+
+/// WM_CLIENT_MACHINE is usually the short hostname, sometimes the fully qualified one.
+#[cfg(target_os = "linux")]
+fn is_remote_host(client_machine: &str, hostname: &str) -> bool {
+    let short = |host: &str| host.split('.').next().unwrap_or(host).to_lowercase();
+    client_machine != "localhost" && short(client_machine) != short(hostname)
+}
+
+/// _NET_WM_PID of a client on another host names a process of that host, not one of our /proc.
+#[cfg(target_os = "linux")]
+fn local_pid(pid: Option<u32>, remote: Option<bool>) -> Option<u32> {
+    pid.filter(|_| remote != Some(true))
+}
+
+/// _NET_DESKTOP_NAMES holds one NUL-terminated name per desktop. Desktops past the end of the
+/// list have no name.
+#[cfg(target_os = "linux")]
+fn parse_desktop_names(raw: &[u8]) -> Vec<String> {
+    let raw = raw.strip_suffix(b"\0").unwrap_or(raw);
+    if raw.is_empty() {
+        return Vec::new();
+    }
+    raw.split(|b| *b == 0).map(decode_text).collect()
+}
+
+/// WM_CLASS holds the instance and class names, each terminated by a NUL byte, as STRING.
+#[cfg(target_os = "linux")]
+fn parse_wm_class(raw: &[u8]) -> Vec<String> {
+    raw.split(|b| *b == 0)
+        .filter(|s| !s.is_empty())
+        .map(decode_latin1)
+        .collect()
+}
+
+/// UTF8_STRING text. Titles may be NUL-terminated by some clients.
+#[cfg(target_os = "linux")]
+fn decode_text(raw: &[u8]) -> String {
+    let raw = raw.strip_suffix(&[0]).unwrap_or(raw);
+    String::from_utf8_lossy(raw).to_string()
+}
+
+/// STRING text, which ICCCM defines as Latin-1.
+#[cfg(target_os = "linux")]
+fn decode_latin1(raw: &[u8]) -> String {
+    let raw = raw.strip_suffix(&[0]).unwrap_or(raw);
+    raw.iter().map(|&b| b as char).collect()
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_wm_class() {
+        assert_eq!(
+            parse_wm_class(b"google-chrome\0Google-chrome\0"),
+            vec!["google-chrome", "Google-chrome"]
+        );
+        assert!(parse_wm_class(b"").is_empty());
+    }
+
+    #[test]
+    fn test_decode_text() {
+        // xprop output used to be split on '=' and trimmed of quotes
+        assert_eq!(decode_text(b"a = \"b\" - Editor"), "a = \"b\" - Editor");
+        assert_eq!(decode_text("Tiếng Việt\0".as_bytes()), "Tiếng Việt");
+        assert_eq!(decode_latin1(b"Caf\xe9 - Editor\0"), "Café - Editor");
+    }
+
+    #[test]
+    fn test_parse_window_type() {
+        assert_eq!(
+            parse_window_type("_NET_WM_WINDOW_TYPE_DIALOG"),
+            WindowType::Dialog
+        );
+        assert_eq!(
+            parse_window_type("_NET_WM_WINDOW_TYPE_POPUP_MENU"),
+            WindowType::Menu
+        );
+        assert_eq!(
+            parse_window_type("_KDE_NET_WM_WINDOW_TYPE_OVERRIDE"),
+            WindowType::Other("_KDE_NET_WM_WINDOW_TYPE_OVERRIDE".to_string())
+        );
+    }
+
+    #[test]
+    fn test_parse_desktop_names() {
+        assert_eq!(
+            parse_desktop_names("screen-inspector\0Thư viện\0\0mail\0".as_bytes()),
+            vec!["screen-inspector", "Thư viện", "", "mail"]
+        );
+        assert!(parse_desktop_names(b"").is_empty());
+    }
+
+    #[test]
+    fn test_is_visible() {
+        let hidden = WindowState {
+            hidden: true,
+            ..Default::default()
+        };
+        assert!(is_visible(true, None, Some(1), Some(1)));
+        // Sticky
+        assert!(is_visible(true, None, None, Some(1)));
+        assert!(!is_visible(true, None, Some(0), Some(1)));
+        // Minimized
+        assert!(!is_visible(false, None, Some(1), Some(1)));
+        assert!(!is_visible(true, Some(hidden), Some(1), Some(1)));
+    }
+
+    #[test]
+    fn test_is_remote_host() {
+        assert!(!is_remote_host("devbox", "devbox"));
+        assert!(!is_remote_host("devbox.corp.example.com", "DevBox"));
+        assert!(!is_remote_host("localhost", "devbox"));
+        assert!(is_remote_host("vdi-pool-17", "devbox"));
+    }
+
+    #[test]
+    fn test_decode_property() {
+        let (string, any, utf8_string) = (AtomEnum::STRING.into(), AtomEnum::ANY.into(), 301);
+        // A _NET_WM_NAME that isn't UTF8_STRING is missing, so WM_NAME is read instead
+        assert_eq!(decode_property(utf8_string, string, b""), None);
+        assert_eq!(
+            decode_property(any, string, b"Caf\xe9").as_deref(),
+            Some("Café")
+        );
+        assert_eq!(
+            decode_property(utf8_string, utf8_string, "Café".as_bytes()).as_deref(),
+            Some("Café")
+        );
+        assert_eq!(decode_property(any, AtomEnum::NONE.into(), b""), None);
+
+        // A WM_CLIENT_MACHINE that isn't STRING doesn't make the window remote
+        let client_machine = decode_property(string, utf8_string, b"");
+        assert_eq!(
+            remote_client(client_machine.as_deref(), Some("devbox\n")),
+            None
+        );
+        assert_eq!(remote_client(Some("devbox"), Some("devbox\n")), Some(false));
+    }
+
+    #[test]
+    fn test_local_pid() {
+        assert_eq!(local_pid(Some(4242), Some(false)), Some(4242));
+        assert_eq!(local_pid(Some(4242), None), Some(4242));
+        // Forwarded over ssh -X, the PID is one of the remote host
+        assert_eq!(local_pid(Some(4242), Some(true)), None);
+    }
 }
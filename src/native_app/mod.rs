@@ -1,10 +1,109 @@
+mod backend;
 mod linux;
 mod darwin;
+mod desktop_entry;
+mod gnome;
+mod hyprland;
+mod icon;
+mod kwin;
+mod multiplexer;
+mod process;
+mod remote;
+mod sway;
+mod terminal;
+mod wayland;
 mod windows;
 
-#[cfg(target_os = "macos")]
-pub use darwin::get_current_window_information;
+use crate::{
+    types::{DesktopEntry, ProcessInfo, TerminalPane},
+    InspectError, WindowEvent, WindowInformation,
+};
+use std::sync::mpsc::Receiver;
+pub use backend::{detect_backends, reset_backend, set_backend, WindowBackend, BACKEND_ENV};
+pub use remote::{find_remote_target, parse_remote_target};
+
+pub fn get_current_window_information() -> Result<Option<WindowInformation>, InspectError> {
+    backend::with_backend(|b| b.current())
+}
+
+pub fn subscribe() -> Result<Receiver<WindowEvent>, InspectError> {
+    backend::with_backend(|b| b.subscribe())
+}
+
+pub fn list_windows() -> Result<Vec<WindowInformation>, InspectError> {
+    backend::with_backend(|b| b.list_windows())
+}
+
+pub fn get_window_icon(
+    window: &WindowInformation,
+    size: u32,
+) -> Result<Option<Vec<u8>>, InspectError> {
+    backend::with_backend(|b| b.icon(window, size))
+}
+
+/// PNG of a themed icon name, as found in desktop entries. Linux only.
+#[cfg(target_os = "linux")]
+pub fn find_theme_icon(name: &str, size: u32) -> Option<Vec<u8>> {
+    icon::find_theme_icon(name, size)
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn find_theme_icon(_name: &str, _size: u32) -> Option<Vec<u8>> {
+    None
+}
+
+/// Command line, user, parents, ... of a process. `None` if it has exited, and on the
+/// platforms without /proc.
+#[cfg(target_os = "linux")]
+pub fn get_process_info(pid: u32) -> Option<ProcessInfo> {
+    process::get_process_info(pid)
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn get_process_info(_pid: u32) -> Option<ProcessInfo> {
+    None
+}
+
+/// Foreground program of the active tab when `process` is a terminal emulator.
+#[cfg(target_os = "linux")]
+pub fn get_terminal_foreground(process: &ProcessInfo) -> Option<ProcessInfo> {
+    if !terminal::is_terminal_emulator(&process.argv) {
+        return None;
+    }
+    terminal::get_foreground_process(process.pid)
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn get_terminal_foreground(_process: &ProcessInfo) -> Option<ProcessInfo> {
+    None
+}
+
+/// Active pane when `process` is a tmux or screen client.
+#[cfg(target_os = "linux")]
+pub fn get_active_pane(process: &ProcessInfo) -> Option<TerminalPane> {
+    multiplexer::get_active_pane(process)
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn get_active_pane(_process: &ProcessInfo) -> Option<TerminalPane> {
+    None
+}
+
+/// Freedesktop desktop entry of the application owning `window`. Linux only.
+#[cfg(target_os = "linux")]
+pub fn get_desktop_entry(window: &WindowInformation) -> Option<DesktopEntry> {
+    desktop_entry::get_desktop_entry(window)
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn get_desktop_entry(_window: &WindowInformation) -> Option<DesktopEntry> {
+    None
+}
+
+/// Path of the executable of a process, read from /proc.
 #[cfg(target_os = "linux")]
-pub use linux::get_current_window_information;
-#[cfg(target_os = "windows")]
-pub use windows::get_current_window_information;
+fn get_exec_path(pid: u32) -> Option<String> {
+    std::fs::read_link(format!("/proc/{}/exe", pid))
+        .ok()
+        .map(|path| path.display().to_string())
+}
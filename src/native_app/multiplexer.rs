@@ -0,0 +1,263 @@
+// Active pane of a terminal multiplexer, when the foreground program of a terminal is a tmux or
+// GNU screen client. The client only relays the pane chosen on the server, so the server is
+// asked which one it is:
+// - tmux: the server socket is /tmp/tmux-<uid>/default, or comes from the client's -L/-S
+//   options and TMUX_TMPDIR. `list-clients` gives the session the client is attached to, and
+//   `list-panes -s` the active pane of the active window of that session, with its command
+//   and path.
+// - screen: `-Q title` on the session named with -r/-x/-S gives the title of the current
+//   window, which is the running command unless the shell renames it. The path isn't exposed.
+#![cfg(target_os = "linux")]
+use {
+    super::process::{read_cmdline, read_stat},
+    crate::types::{Multiplexer, ProcessInfo, TerminalPane},
+    anyhow::{anyhow, Result},
+    std::{fs, path::Path, path::PathBuf, process::Command},
+};
+
+const PANE_FORMAT: &str =
+    "#{window_active}\t#{pane_active}\t#{pane_pid}\t#{pane_current_command}\t#{pane_current_path}";
+
+/// Active pane when `client` is a tmux or screen client.
+pub fn get_active_pane(client: &ProcessInfo) -> Option<TerminalPane> {
+    let name = client.argv.first().map(Path::new)?.file_name()?.to_str()?;
+    let pane = match name {
+        "tmux" => get_tmux_pane(client),
+        "screen" => get_screen_pane(client),
+        _ => return None,
+    };
+    pane.map_err(|e| log::debug!("Cannot read the {} pane: {}", name, e))
+        .ok()
+}
+
+fn get_tmux_pane(client: &ProcessInfo) -> Result<TerminalPane> {
+    let tmpdir = read_environ(client.pid, "TMUX_TMPDIR").unwrap_or_else(|| "/tmp".into());
+    let socket = tmux_socket_path(&client.argv, &tmpdir, client.uid.unwrap_or(0));
+    let tmux = |args: &[&str]| -> Result<String> {
+        let output = Command::new("tmux")
+            .arg("-S")
+            .arg(&socket)
+            .args(args)
+            .output()?;
+        if !output.status.success() {
+            return Err(anyhow!(
+                "tmux {}: {}",
+                args[0],
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    };
+
+    let clients = tmux(&["list-clients", "-F", "#{client_pid}\t#{session_name}"])?;
+    let session = clients
+        .lines()
+        .filter_map(|line| line.split_once('\t'))
+        .find(|(pid, _)| pid.parse() == Ok(client.pid))
+        .map(|(_, session)| session.to_string())
+        .ok_or(anyhow!(
+            "Client {} not attached to {}",
+            client.pid,
+            socket.display()
+        ))?;
+    let panes = tmux(&["list-panes", "-s", "-t", &session, "-F", PANE_FORMAT])?;
+    let mut pane = parse_active_pane(&panes).ok_or(anyhow!("No active pane in {}", session))?;
+    pane.session = Some(session);
+    // The program in the foreground of the pane's PTY, the shell itself at the prompt
+    pane.argv = pane
+        .pid
+        .and_then(read_stat)
+        .filter(|stat| stat.tpgid > 0)
+        .map(|stat| read_cmdline(stat.tpgid as u32))
+        .unwrap_or_default();
+    Ok(pane)
+}
+
+fn get_screen_pane(client: &ProcessInfo) -> Result<TerminalPane> {
+    let session = screen_session(&client.argv);
+    let mut command = Command::new("screen");
+    if let Some(session) = &session {
+        command.args(["-S", session]);
+    }
+    let output = command.args(["-Q", "title"]).output()?;
+    if !output.status.success() {
+        return Err(anyhow!("screen -Q title failed"));
+    }
+    let title = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    Ok(TerminalPane {
+        multiplexer: Multiplexer::Screen,
+        session,
+        pid: None,
+        command: Some(title).filter(|title| !title.is_empty()),
+        argv: Vec::new(),
+        path: None,
+    })
+}
+
+/// Socket of the server a tmux client talks to, from its -S path or -L name.
+fn tmux_socket_path(argv: &[String], tmpdir: &str, uid: u32) -> PathBuf {
+    let mut name = "default".to_string();
+    let mut args = argv.iter().skip(1);
+    while let Some(arg) = args.next() {
+        // Options end at the command, e.g. "attach"
+        let Some(flags) = arg.strip_prefix('-') else {
+            break;
+        };
+        // Flags may be grouped, the ones taking a value end the group: -2uS path, -Lwork
+        for (i, flag) in flags.char_indices() {
+            if !"cfLST".contains(flag) {
+                continue;
+            }
+            let value = match &flags[i + 1..] {
+                "" => args.next().cloned().unwrap_or_default(),
+                rest => rest.to_string(),
+            };
+            match flag {
+                'S' => return PathBuf::from(value),
+                'L' => name = value,
+                _ => {}
+            }
+            break;
+        }
+    }
+    Path::new(tmpdir).join(format!("tmux-{}", uid)).join(name)
+}
+
+/// Pane of `list-panes -F PANE_FORMAT` which is both in the active window and active in it.
+fn parse_active_pane(panes: &str) -> Option<TerminalPane> {
+    panes.lines().find_map(|line| {
+        let mut fields = line.splitn(5, '\t');
+        let window_active = fields.next()?;
+        let pane_active = fields.next()?;
+        if window_active != "1" || pane_active != "1" {
+            return None;
+        }
+        Some(TerminalPane {
+            multiplexer: Multiplexer::Tmux,
+            session: None,
+            pid: fields.next()?.parse().ok(),
+            command: fields.next().map(str::to_string),
+            argv: Vec::new(),
+            path: fields.next().map(str::to_string),
+        })
+    })
+}
+
+/// Session named with `screen -r <name>`, `-x <name>` or `-S <name>`.
+fn screen_session(argv: &[String]) -> Option<String> {
+    let option = argv
+        .iter()
+        .position(|arg| matches!(arg.as_str(), "-r" | "-R" | "-x" | "-S"))?;
+    argv.get(option + 1)
+        .filter(|name| !name.starts_with('-'))
+        .cloned()
+}
+
+fn read_environ(pid: u32, name: &str) -> Option<String> {
+    let environ = fs::read(format!("/proc/{}/environ", pid)).ok()?;
+    environ.split(|b| *b == 0).find_map(|var| {
+        let value = var.strip_prefix(name.as_bytes())?.strip_prefix(b"=")?;
+        Some(String::from_utf8_lossy(value).to_string())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::terminal::get_foreground_process;
+    use super::*;
+    use crate::test_util::TempDir;
+    use std::{
+        process::Stdio,
+        thread::sleep,
+        time::{Duration, Instant},
+    };
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|a| a.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse_client_args() {
+        assert_eq!(
+            tmux_socket_path(&args(&["tmux", "attach"]), "/tmp", 1000),
+            PathBuf::from("/tmp/tmux-1000/default")
+        );
+        assert_eq!(
+            tmux_socket_path(
+                &args(&["tmux", "-2", "-L", "work", "a"]),
+                "/run/user/1000",
+                1000
+            ),
+            PathBuf::from("/run/user/1000/tmux-1000/work")
+        );
+        assert_eq!(
+            tmux_socket_path(
+                &args(&["tmux", "-uS/srv/pair.sock", "attach"]),
+                "/tmp",
+                1000
+            ),
+            PathBuf::from("/srv/pair.sock")
+        );
+        assert_eq!(
+            screen_session(&args(&["screen", "-x", "build"])).as_deref(),
+            Some("build")
+        );
+    }
+
+    #[test]
+    fn test_tmux_pane() {
+        let dir = TempDir::new("tmux");
+        let socket = dir.path().join("socket").display().to_string();
+        let tmux = |extra: &[&str]| {
+            Command::new("tmux")
+                .args(["-S", &socket, "-f", "/dev/null"])
+                .args(extra)
+                .status()
+        };
+        let Ok(status) = tmux(&["new-session", "-d", "-s", "work", "-c", "/tmp", "sleep 30"])
+        else {
+            eprintln!("tmux not available, skipping");
+            return;
+        };
+        assert!(status.success());
+        // Not selected, the first window stays active
+        tmux(&["new-window", "-d", "-t", "work", "-c", "/", "sleep 31"]).unwrap();
+        // script(1) plays the terminal the client is attached from
+        let terminal = Command::new("script")
+            .args([
+                "-q",
+                "-c",
+                &format!("tmux -S {} attach -t work", socket),
+                "/dev/null",
+            ])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .spawn();
+        let Ok(mut terminal) = terminal else {
+            tmux(&["kill-server"]).unwrap();
+            eprintln!("script not available, skipping");
+            return;
+        };
+        // The client shows up before its terminal is in the foreground
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let pane = loop {
+            let pane = get_foreground_process(terminal.id())
+                .as_ref()
+                .and_then(get_active_pane);
+            if pane.is_some() || Instant::now() > deadline {
+                break pane;
+            }
+            sleep(Duration::from_millis(20));
+        };
+        tmux(&["kill-server"]).unwrap();
+        terminal.kill().unwrap();
+        terminal.wait().unwrap();
+
+        let pane = pane.expect("No tmux pane found for the attached client within 5s");
+        assert_eq!(pane.multiplexer, Multiplexer::Tmux);
+        assert_eq!(pane.session.as_deref(), Some("work"));
+        assert_eq!(pane.command.as_deref(), Some("sleep"));
+        assert_eq!(pane.argv, vec!["sleep", "30"]);
+        assert_eq!(pane.path.as_deref(), Some("/tmp"));
+    }
+}
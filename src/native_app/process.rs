@@ -0,0 +1,202 @@
+// Details of the process owning a window, read from /proc:
+// - /proc/<pid>/cmdline: NUL separated argv
+// - /proc/<pid>/cwd: symlink to the working directory
+// - /proc/<pid>/stat: ppid, session, CPU times and start time in clock ticks since boot.
+//   The command name is in parentheses and may contain spaces or parentheses itself,
+//   so the fields are counted from the last ')'.
+// - /proc/<pid>/status: real uid and resident memory (VmRSS)
+// - /proc/stat: boot time (btime), to turn the start time into a Unix timestamp
+#![cfg(target_os = "linux")]
+use {
+    crate::types::ProcessInfo,
+    std::{fs, sync::OnceLock, time::Duration},
+};
+
+/// Clock ticks per second of the times in /proc/<pid>/stat (USER_HZ).
+fn clock_ticks() -> u64 {
+    static CLOCK_TICKS: OnceLock<u64> = OnceLock::new();
+    // SAFETY: sysconf only reads a system setting
+    *CLOCK_TICKS.get_or_init(|| match unsafe { libc::sysconf(libc::_SC_CLK_TCK) } {
+        ticks if ticks > 0 => ticks as u64,
+        _ => 100,
+    })
+}
+
+/// Fields of /proc/<pid>/stat the crate uses.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Stat {
+    pub comm: String,
+    pub ppid: u32,
+    pub session: u32,
+    /// Foreground process group of the controlling terminal, -1 without a terminal.
+    pub tpgid: i32,
+    pub utime: u64,
+    pub stime: u64,
+    pub starttime: u64,
+}
+
+pub fn read_stat(pid: u32) -> Option<Stat> {
+    parse_stat(&fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?)
+}
+
+/// `None` when the process has exited.
+pub fn get_process_info(pid: u32) -> Option<ProcessInfo> {
+    let stat = read_stat(pid)?;
+    let argv = read_cmdline(pid);
+    let cwd = fs::read_link(format!("/proc/{}/cwd", pid))
+        .ok()
+        .map(|path| path.display().to_string());
+    let status = fs::read_to_string(format!("/proc/{}/status", pid)).unwrap_or_default();
+    let uid = status_field(&status, "Uid").and_then(|uid| uid.parse().ok());
+    let user = uid.and_then(|uid| {
+        let passwd = fs::read_to_string("/etc/passwd").ok()?;
+        find_user_name(&passwd, uid)
+    });
+    let rss = status_field(&status, "VmRSS")
+        .and_then(|rss| rss.trim_end_matches(" kB").parse::<u64>().ok())
+        .map(|kb| kb * 1024);
+    let ticks = clock_ticks();
+    let start_time = boot_time().map(|btime| btime + stat.starttime / ticks);
+
+    Some(ProcessInfo {
+        pid,
+        ppid: Some(stat.ppid).filter(|ppid| *ppid != 0),
+        ancestors: ancestors(pid, &stat),
+        argv,
+        cwd,
+        uid,
+        user,
+        start_time,
+        rss,
+        cpu_time: Some(Duration::from_millis(
+            (stat.utime + stat.stime) * 1000 / ticks,
+        )),
+    })
+}
+
+/// Arguments of a process, empty when it has exited or can't be read.
+pub fn read_cmdline(pid: u32) -> Vec<String> {
+    fs::read(format!("/proc/{}/cmdline", pid))
+        .map(|cmdline| parse_cmdline(&cmdline))
+        .unwrap_or_default()
+}
+
+/// Parent PIDs from the direct parent up to the session leader, or up to init when the
+/// process isn't in a session of its own.
+fn ancestors(pid: u32, stat: &Stat) -> Vec<u32> {
+    let mut ancestors = Vec::new();
+    let (mut current, mut parent) = (pid, stat.ppid);
+    while current != stat.session && parent > 1 && !ancestors.contains(&parent) {
+        ancestors.push(parent);
+        current = parent;
+        parent = match read_stat(parent) {
+            Some(stat) => stat.ppid,
+            None => break,
+        };
+    }
+    ancestors
+}
+
+fn boot_time() -> Option<u64> {
+    let stat = fs::read_to_string("/proc/stat").ok()?;
+    stat.lines()
+        .find_map(|line| line.strip_prefix("btime "))
+        .and_then(|btime| btime.trim().parse().ok())
+}
+
+fn parse_stat(content: &str) -> Option<Stat> {
+    let open = content.find('(')?;
+    let close = content.rfind(')')?;
+    let comm = content.get(open + 1..close)?.to_string();
+    // Fields from the state, which is field 3 in proc(5)
+    let fields: Vec<&str> = content.get(close + 1..)?.split_whitespace().collect();
+    let field = |number: usize| fields.get(number - 3).copied();
+    Some(Stat {
+        comm,
+        ppid: field(4)?.parse().ok()?,
+        session: field(6)?.parse().ok()?,
+        tpgid: field(8)?.parse().ok()?,
+        utime: field(14)?.parse().ok()?,
+        stime: field(15)?.parse().ok()?,
+        starttime: field(22)?.parse().ok()?,
+    })
+}
+
+fn parse_cmdline(cmdline: &[u8]) -> Vec<String> {
+    if cmdline.is_empty() {
+        return Vec::new();
+    }
+    cmdline
+        .strip_suffix(b"\0")
+        .unwrap_or(cmdline)
+        .split(|b| *b == 0)
+        .map(|arg| String::from_utf8_lossy(arg).to_string())
+        .collect()
+}
+
+/// First value of a "Name:\tvalue" line of /proc/<pid>/status.
+fn status_field<'a>(status: &'a str, name: &str) -> Option<&'a str> {
+    status.lines().find_map(|line| {
+        let value = line.strip_prefix(name)?.strip_prefix(':')?;
+        value.split('\t').map(str::trim).find(|v| !v.is_empty())
+    })
+}
+
+fn find_user_name(passwd: &str, uid: u32) -> Option<String> {
+    passwd.lines().find_map(|line| {
+        let mut fields = line.split(':');
+        let name = fields.next()?;
+        (fields.nth(1)?.parse() == Ok(uid)).then(|| name.to_string())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_stat() {
+        let content = "4242 (Web Content (1)) S 4100 4242 4000 34816 4300 4194560 \
+                       1520 0 0 0 250 75 0 0 20 0 32 0 123456 2516582400 61440";
+        let stat = parse_stat(content).unwrap();
+        assert_eq!(
+            stat,
+            Stat {
+                comm: "Web Content (1)".to_string(),
+                ppid: 4100,
+                session: 4000,
+                tpgid: 4300,
+                utime: 250,
+                stime: 75,
+                starttime: 123456,
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_proc_files() {
+        assert_eq!(
+            parse_cmdline(b"python3\0-m\0http.server\0"),
+            vec!["python3", "-m", "http.server"]
+        );
+        assert!(parse_cmdline(b"").is_empty());
+
+        let status = "Name:\tbash\nUid:\t1000\t1000\t1000\t1000\nVmRSS:\t    5120 kB\n";
+        assert_eq!(status_field(status, "Uid"), Some("1000"));
+        assert_eq!(status_field(status, "VmRSS"), Some("5120 kB"));
+
+        let passwd = "root:x:0:0:root:/root:/bin/bash\nvinh:x:1000:1000::/home/vinh:/bin/zsh\n";
+        assert_eq!(find_user_name(passwd, 1000).as_deref(), Some("vinh"));
+        assert_eq!(find_user_name(passwd, 1001), None);
+    }
+
+    #[test]
+    fn test_get_process_info() {
+        let info = get_process_info(std::process::id()).unwrap();
+        assert!(!info.argv.is_empty());
+        let cwd = std::env::current_dir().unwrap();
+        assert_eq!(info.cwd, Some(cwd.display().to_string()));
+        assert!(info.rss.is_some_and(|rss| rss > 0));
+        assert!(info.start_time.is_some());
+    }
+}
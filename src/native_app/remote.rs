@@ -0,0 +1,429 @@
+// Destination of remote sessions, parsed from the command line of the client:
+// - ssh: first argument that isn't an option, `[user@]host` or `ssh://[user@]host[:port]`,
+//   with -l and -p
+// - mosh: the perl wrapper takes `[user@]host` like ssh, then execs mosh-client with the
+//   original arguments in argv[0], "mosh-client -# <args> |", then the resolved address and port
+// - RDP: xfreerdp and friends take /v:host[:port] and /u:[domain\]user, rdesktop a host
+//   argument and -u
+// - VNC: vncviewer takes host, host:display (port 5900 + display, or the port itself from 100 up)
+//   or host::port
+// - remmina: a URI (rdp://, vnc://, ssh://) or a .remmina file with server and username keys
+use {
+    crate::types::{RemoteProtocol, RemoteTarget, WindowInformation},
+    std::path::Path,
+};
+
+/// ssh options followed by a value, from ssh(1).
+const SSH_VALUE_OPTIONS: &str = "BbcDEeFIiJLlmOopQRSWw";
+
+/// mosh options taking a value, either after '=' or as the next argument.
+const MOSH_VALUE_OPTIONS: [&str; 9] = [
+    "-p",
+    "--port",
+    "--ssh",
+    "--predict",
+    "--bind-server",
+    "--family",
+    "--server",
+    "--client",
+    "--experimental-remote-ip",
+];
+
+/// vncviewer options followed by a value, from TigerVNC's vncviewer(1). Matched case-insensitively
+/// like vncviewer does, with one or two dashes.
+const VNC_VALUE_OPTIONS: [&str; 18] = [
+    "passwd",
+    "passwordfile",
+    "via",
+    "display",
+    "geometry",
+    "securitytypes",
+    "x509ca",
+    "x509crl",
+    "preferredencoding",
+    "qualitylevel",
+    "compresslevel",
+    "fullscreenmode",
+    "fullscreenselectedmonitors",
+    "desktopsize",
+    "menukey",
+    "log",
+    "pointereventinterval",
+    "config",
+];
+
+/// Parse the arguments of a remote session client. `None` for any other program, or when the
+/// destination can't be told.
+pub fn parse_remote_target(argv: &[String]) -> Option<RemoteTarget> {
+    let argv = strip_interpreter(argv);
+    if let Some(original) = parse_mosh_client(argv.first()?) {
+        return parse_mosh(&original);
+    }
+    let program = Path::new(argv.first()?).file_name()?.to_str()?;
+    match program {
+        "ssh" | "autossh" => parse_ssh(&argv[1..]),
+        "mosh" => parse_mosh(&argv[1..]),
+        "xfreerdp" | "xfreerdp3" | "wlfreerdp" | "wlfreerdp3" | "sdl-freerdp" | "sdl-freerdp3" => {
+            parse_freerdp(&argv[1..])
+        }
+        "rdesktop" => parse_rdesktop(&argv[1..]),
+        "vncviewer" | "xtigervncviewer" | "xvncviewer" | "xtightvncviewer" => {
+            parse_vncviewer(&argv[1..])
+        }
+        "remmina" => parse_remmina(&argv[1..]),
+        _ => None,
+    }
+}
+
+/// Remote session of a window: the program in a tmux pane, in a terminal, or the window's
+/// own process, from the innermost to the outermost.
+pub fn find_remote_target(window: &WindowInformation) -> Option<RemoteTarget> {
+    let pane = window.pane.as_ref().map(|pane| &pane.argv);
+    let foreground = window.foreground_process.as_ref().map(|p| &p.argv);
+    let process = window.process.as_ref().map(|p| &p.argv);
+    [pane, foreground, process]
+        .into_iter()
+        .flatten()
+        .find_map(|argv| parse_remote_target(argv))
+}
+
+/// mosh is a perl script, its argv starts with the interpreter.
+fn strip_interpreter(argv: &[String]) -> &[String] {
+    let interpreter = argv
+        .first()
+        .and_then(|arg| Path::new(arg).file_name()?.to_str())
+        .is_some_and(|name| name.starts_with("perl") || name.starts_with("python"));
+    if interpreter && argv.len() > 1 {
+        &argv[1..]
+    } else {
+        argv
+    }
+}
+
+fn parse_ssh(args: &[String]) -> Option<RemoteTarget> {
+    let (mut user, mut port, mut destination) = (None, None, None);
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let Some(flags) = arg.strip_prefix('-').filter(|flags| !flags.is_empty()) else {
+            destination = Some(arg.as_str());
+            break;
+        };
+        // Grouped flags end with the one taking a value: -vp 2222, -p2222
+        for (i, flag) in flags.char_indices() {
+            if !SSH_VALUE_OPTIONS.contains(flag) {
+                continue;
+            }
+            let value = match &flags[i + 1..] {
+                "" => args.next().cloned(),
+                rest => Some(rest.to_string()),
+            };
+            match flag {
+                'l' => user = value,
+                'p' => port = value.and_then(|p| p.parse().ok()),
+                _ => {}
+            }
+            break;
+        }
+    }
+    let destination = destination?;
+    let mut target = parse_destination(destination.strip_prefix("ssh://").unwrap_or(destination))?;
+    target.user = target.user.or(user);
+    target.port = target.port.or(port);
+    Some(RemoteTarget {
+        protocol: RemoteProtocol::Ssh,
+        ..target
+    })
+}
+
+fn parse_mosh(args: &[String]) -> Option<RemoteTarget> {
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if arg == "--" {
+            return None;
+        }
+        if MOSH_VALUE_OPTIONS.contains(&arg.as_str()) {
+            args.next();
+        } else if !arg.starts_with('-') {
+            return Some(RemoteTarget {
+                protocol: RemoteProtocol::Mosh,
+                ..parse_destination(arg)?
+            });
+        }
+    }
+    None
+}
+
+/// Arguments given to mosh, from the argv[0] of the mosh-client it started.
+fn parse_mosh_client(program: &str) -> Option<Vec<String>> {
+    let (client, original) = program.split_once(" -# ")?;
+    if Path::new(client).file_name()? != "mosh-client" {
+        return None;
+    }
+    let original = original
+        .trim_end()
+        .trim_end_matches('|')
+        .split_whitespace()
+        .map(str::to_string)
+        .collect();
+    Some(original)
+}
+
+fn parse_freerdp(args: &[String]) -> Option<RemoteTarget> {
+    let value = |name: &str| {
+        args.iter().find_map(|arg| {
+            let arg = arg.strip_prefix('/').or(arg.strip_prefix('-'))?;
+            arg.strip_prefix(name)?.strip_prefix(':')
+        })
+    };
+    let mut target = parse_host_port(value("v")?)?;
+    target.user = value("u").map(strip_domain);
+    target.port = target.port.or(value("port").and_then(|p| p.parse().ok()));
+    Some(RemoteTarget {
+        protocol: RemoteProtocol::Rdp,
+        ..target
+    })
+}
+
+fn parse_rdesktop(args: &[String]) -> Option<RemoteTarget> {
+    let user = args
+        .iter()
+        .position(|arg| arg == "-u")
+        .and_then(|i| args.get(i + 1))
+        .map(|user| strip_domain(user));
+    let mut target = parse_host_port(args.last().filter(|arg| !arg.starts_with('-'))?)?;
+    target.user = user;
+    Some(RemoteTarget {
+        protocol: RemoteProtocol::Rdp,
+        ..target
+    })
+}
+
+fn parse_vncviewer(args: &[String]) -> Option<RemoteTarget> {
+    let mut server = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.strip_prefix('-') {
+            Some(option) => {
+                let option = option.trim_start_matches('-').to_lowercase();
+                if VNC_VALUE_OPTIONS.contains(&option.as_str()) {
+                    args.next();
+                }
+            }
+            None => server = Some(arg),
+        }
+    }
+    let server = server?;
+    let (host, port) = match server.split_once("::") {
+        Some((host, port)) => (host, port.parse().ok()),
+        None => match server.rsplit_once(':') {
+            Some((host, display)) => (
+                host,
+                display
+                    .parse::<u16>()
+                    .ok()
+                    .map(|d| if d < 100 { 5900 + d } else { d }),
+            ),
+            None => (server.as_str(), None),
+        },
+    };
+    Some(RemoteTarget {
+        protocol: RemoteProtocol::Vnc,
+        host: host.to_string(),
+        user: None,
+        port,
+    })
+    .filter(|target| !target.host.is_empty())
+}
+
+fn parse_remmina(args: &[String]) -> Option<RemoteTarget> {
+    let connection = args
+        .iter()
+        .position(|arg| arg == "-c" || arg == "--connect")
+        .and_then(|i| args.get(i + 1))?;
+    if let Some((scheme, rest)) = connection.split_once("://") {
+        let protocol = match scheme {
+            "rdp" => RemoteProtocol::Rdp,
+            "vnc" => RemoteProtocol::Vnc,
+            "ssh" | "sftp" => RemoteProtocol::Ssh,
+            _ => return None,
+        };
+        return Some(RemoteTarget {
+            protocol,
+            ..parse_destination(rest.split('/').next()?)?
+        });
+    }
+
+    let profile = std::fs::read_to_string(connection).ok()?;
+    let key = |name: &str| {
+        profile.lines().find_map(|line| {
+            let (key, value) = line.split_once('=')?;
+            (key.trim() == name).then(|| value.trim().to_string())
+        })
+    };
+    let protocol = match key("protocol")?.to_uppercase().as_str() {
+        "RDP" => RemoteProtocol::Rdp,
+        "VNC" => RemoteProtocol::Vnc,
+        "SSH" | "SFTP" => RemoteProtocol::Ssh,
+        _ => return None,
+    };
+    let mut target = parse_host_port(&key("server")?)?;
+    target.user = key("username").filter(|user| !user.is_empty());
+    Some(RemoteTarget { protocol, ..target })
+}
+
+/// `[user@]host[:port]`
+fn parse_destination(destination: &str) -> Option<RemoteTarget> {
+    match destination.rsplit_once('@') {
+        Some((user, host)) => Some(RemoteTarget {
+            user: Some(user.to_string()),
+            ..parse_host_port(host)?
+        }),
+        None => parse_host_port(destination),
+    }
+}
+
+/// `host`, `host:port`, `[v6 address]` or `[v6 address]:port`. A bare IPv6 address has no port.
+fn parse_host_port(address: &str) -> Option<RemoteTarget> {
+    let (host, port) = if let Some(rest) = address.strip_prefix('[') {
+        let (host, rest) = rest.split_once(']')?;
+        (host, rest.strip_prefix(':'))
+    } else {
+        match address.split_once(':') {
+            Some((host, port)) if !port.contains(':') => (host, Some(port)),
+            _ => (address, None),
+        }
+    };
+    if host.is_empty() {
+        return None;
+    }
+    Some(RemoteTarget {
+        protocol: RemoteProtocol::Ssh,
+        host: host.to_string(),
+        user: None,
+        port: port.and_then(|port| port.parse().ok()),
+    })
+}
+
+/// `DOMAIN\user` -> `user`
+fn strip_domain(user: &str) -> String {
+    user.rsplit_once('\\')
+        .map_or(user, |(_, user)| user)
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(command: &str) -> Option<RemoteTarget> {
+        parse_argv(&command.split(' ').collect::<Vec<_>>())
+    }
+
+    fn parse_argv(argv: &[&str]) -> Option<RemoteTarget> {
+        let argv: Vec<String> = argv.iter().map(|arg| arg.to_string()).collect();
+        parse_remote_target(&argv)
+    }
+
+    fn target(
+        protocol: RemoteProtocol,
+        user: Option<&str>,
+        host: &str,
+        port: Option<u16>,
+    ) -> RemoteTarget {
+        RemoteTarget {
+            protocol,
+            host: host.to_string(),
+            user: user.map(str::to_string),
+            port,
+        }
+    }
+
+    #[test]
+    fn test_ssh() {
+        use RemoteProtocol::Ssh;
+        assert_eq!(
+            parse("ssh deploy@prod-db-3"),
+            Some(target(Ssh, Some("deploy"), "prod-db-3", None))
+        );
+        assert_eq!(
+            parse("/usr/bin/ssh -A -i /home/vinh/.ssh/id_ed25519 -p 2222 -l admin bastion -t tmux"),
+            Some(target(Ssh, Some("admin"), "bastion", Some(2222)))
+        );
+        assert_eq!(
+            parse("ssh -vp2222 ssh://git@[2001:db8::1]:2200"),
+            Some(target(Ssh, Some("git"), "2001:db8::1", Some(2200)))
+        );
+        assert_eq!(parse("ssh -V"), None);
+    }
+
+    #[test]
+    fn test_mosh() {
+        use RemoteProtocol::Mosh;
+        assert_eq!(
+            parse_argv(&[
+                "/usr/bin/perl",
+                "/usr/bin/mosh",
+                "--ssh=ssh -p 2222",
+                "-p",
+                "60001",
+                "--predict",
+                "always",
+                "vinh@build-box",
+                "--",
+                "tmux",
+                "a"
+            ]),
+            Some(target(Mosh, Some("vinh"), "build-box", None))
+        );
+        assert_eq!(
+            parse_argv(&[
+                "mosh-client -# --family inet vinh@build-box -- tmux a |",
+                "10.0.0.7",
+                "60001"
+            ]),
+            Some(target(Mosh, Some("vinh"), "build-box", None))
+        );
+        assert_eq!(
+            parse("mosh --ssh ssh -p 2222 build-box"),
+            Some(target(Mosh, None, "build-box", None))
+        );
+    }
+
+    #[test]
+    fn test_remote_desktop() {
+        use RemoteProtocol::{Rdp, Vnc};
+        assert_eq!(
+            parse("xfreerdp /u:CORP\\vinh /p:secret /v:win-desk-12:3390 /f"),
+            Some(target(Rdp, Some("vinh"), "win-desk-12", Some(3390)))
+        );
+        assert_eq!(
+            parse("rdesktop -u admin -g 1920x1080 10.1.2.3"),
+            Some(target(Rdp, Some("admin"), "10.1.2.3", None))
+        );
+        assert_eq!(
+            parse("vncviewer -FullScreen lab-pc:1"),
+            Some(target(Vnc, None, "lab-pc", Some(5901)))
+        );
+        assert_eq!(
+            parse("vncviewer lab-pc:5901"),
+            Some(target(Vnc, None, "lab-pc", Some(5901)))
+        );
+        assert_eq!(
+            parse("vncviewer lab-pc::5999"),
+            Some(target(Vnc, None, "lab-pc", Some(5999)))
+        );
+        assert_eq!(
+            parse("vncviewer lab-pc:1 -passwd /home/vinh/.vnc/passwd -Shared"),
+            Some(target(Vnc, None, "lab-pc", Some(5901)))
+        );
+        assert_eq!(
+            parse("vncviewer -via gateway --QualityLevel 8 -PasswordFile=/tmp/pw lab-pc"),
+            Some(target(Vnc, None, "lab-pc", None))
+        );
+        assert_eq!(
+            parse("remmina -c rdp://vinh@win-desk-12"),
+            Some(target(Rdp, Some("vinh"), "win-desk-12", None))
+        );
+        assert_eq!(parse("code --new-window"), None);
+    }
+}
@@ -0,0 +1,411 @@
+// sway / i3 backend over the i3 IPC protocol, on the Unix socket from $SWAYSOCK or $I3SOCK.
+// Every message is "i3-ipc", then the payload length and the message type as native-endian u32,
+// then a JSON payload:
+// - GET_TREE: the layout tree, root > outputs > workspaces > containers. The focused window is
+//   the node with `"focused": true`. Native Wayland windows have an `app_id`, X11 ones
+//   (i3, XWayland) have `window_properties` with the WM_CLASS instance and class.
+// - SUBSCRIBE to ["window"]: a `window` event (type 0x80000003) for each change of a window,
+//   with the kind of change and the container. The container doesn't tell its workspace or
+//   output, so the tree is queried again for every focus or title change.
+#![cfg(target_os = "linux")]
+use {
+    super::{get_exec_path, WindowBackend},
+    crate::{types::Geometry, types::Workspace, WindowEvent, WindowInformation},
+    anyhow::{anyhow, Result},
+    serde_json::Value,
+    std::io::{Read, Write},
+    std::os::unix::net::UnixStream,
+    std::path::{Path, PathBuf},
+    std::sync::mpsc::{self, Receiver, Sender},
+    std::thread,
+    std::time::SystemTime,
+};
+
+const MAGIC: &[u8; 6] = b"i3-ipc";
+const SUBSCRIBE: u32 = 2;
+const GET_TREE: u32 = 4;
+/// Events have the highest bit of the type set
+const EVENT_WINDOW: u32 = 0x8000_0003;
+
+/// Socket of the running sway or i3 session.
+pub fn socket_path() -> Option<PathBuf> {
+    std::env::var_os("SWAYSOCK")
+        .or_else(|| std::env::var_os("I3SOCK"))
+        .map(PathBuf::from)
+}
+
+pub struct SwayBackend;
+
+impl WindowBackend for SwayBackend {
+    fn name(&self) -> &'static str {
+        "sway"
+    }
+
+    fn current(&self) -> Result<Option<WindowInformation>> {
+        get_current_window_information()
+    }
+
+    fn subscribe(&self) -> Result<Receiver<WindowEvent>> {
+        subscribe()
+    }
+
+    fn list_windows(&self) -> Result<Vec<WindowInformation>> {
+        let path = socket_path().ok_or(anyhow!("Neither SWAYSOCK nor I3SOCK is set"))?;
+        IpcConnection::connect(&path)?.list_windows()
+    }
+}
+
+/// Focused window from the layout tree. `None` when an empty workspace has the focus.
+pub fn get_current_window_information() -> Result<Option<WindowInformation>> {
+    let path = socket_path().ok_or(anyhow!("Neither SWAYSOCK nor I3SOCK is set"))?;
+    IpcConnection::connect(&path)?.get_focused_window()
+}
+
+/// Receive an event each time the focused window or its title changes.
+/// The watching thread stops once the receiver is dropped and the next event arrives.
+pub fn subscribe() -> Result<Receiver<WindowEvent>> {
+    let path = socket_path().ok_or(anyhow!("Neither SWAYSOCK nor I3SOCK is set"))?;
+    let events = IpcConnection::connect(&path)?;
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        if let Err(e) = watch(events, &path, tx) {
+            log::error!("sway IPC event loop stopped: {}", e);
+        }
+    });
+    Ok(rx)
+}
+
+fn watch(mut events: IpcConnection, path: &Path, tx: Sender<WindowEvent>) -> Result<()> {
+    let reply = events.request(SUBSCRIBE, r#"["window"]"#)?;
+    if reply["success"] != Value::Bool(true) {
+        return Err(anyhow!("Subscription refused: {}", reply));
+    }
+    // Replies and events can't be told apart on a subscribed connection, so the tree is
+    // read over a second one
+    let mut queries = IpcConnection::connect(path)?;
+
+    loop {
+        let (kind, event) = events.receive()?;
+        if kind != EVENT_WINDOW {
+            continue;
+        }
+        let focused = event["container"]["focused"] == Value::Bool(true);
+        let window_event = match event["change"].as_str() {
+            Some("focus") => queries.get_focused_window()?.map(WindowEvent::FocusChanged),
+            Some("title") if focused => {
+                queries.get_focused_window()?.map(WindowEvent::TitleChanged)
+            }
+            _ => None,
+        };
+        let Some(window_event) = window_event else {
+            continue;
+        };
+        if tx.send(window_event).is_err() {
+            // Receiver dropped
+            return Ok(());
+        }
+    }
+}
+
+struct IpcConnection {
+    stream: UnixStream,
+}
+
+impl IpcConnection {
+    fn connect(path: &Path) -> Result<Self> {
+        let stream = UnixStream::connect(path)
+            .map_err(|e| anyhow!("Cannot connect to {}: {}", path.display(), e))?;
+        Ok(IpcConnection { stream })
+    }
+
+    fn request(&mut self, kind: u32, payload: &str) -> Result<Value> {
+        let mut message = Vec::with_capacity(14 + payload.len());
+        message.extend_from_slice(MAGIC);
+        message.extend_from_slice(&(payload.len() as u32).to_ne_bytes());
+        message.extend_from_slice(&kind.to_ne_bytes());
+        message.extend_from_slice(payload.as_bytes());
+        self.stream.write_all(&message)?;
+
+        let (reply_kind, reply) = self.receive()?;
+        if reply_kind != kind {
+            return Err(anyhow!(
+                "Expected a reply of type {}, got {}",
+                kind,
+                reply_kind
+            ));
+        }
+        Ok(reply)
+    }
+
+    fn receive(&mut self) -> Result<(u32, Value)> {
+        let mut header = [0u8; 14];
+        self.stream.read_exact(&mut header)?;
+        if &header[..6] != MAGIC {
+            return Err(anyhow!("Invalid i3 IPC header"));
+        }
+        let len = u32::from_ne_bytes([header[6], header[7], header[8], header[9]]);
+        let kind = u32::from_ne_bytes([header[10], header[11], header[12], header[13]]);
+        let mut payload = vec![0u8; len as usize];
+        self.stream.read_exact(&mut payload)?;
+        Ok((kind, serde_json::from_slice(&payload)?))
+    }
+
+    fn get_focused_window(&mut self) -> Result<Option<WindowInformation>> {
+        let tree = self.request(GET_TREE, "")?;
+        Ok(find_focused(&tree))
+    }
+
+    fn list_windows(&mut self) -> Result<Vec<WindowInformation>> {
+        let tree = self.request(GET_TREE, "")?;
+        let mut windows = Vec::new();
+        find_windows(&tree, None, None, false, &mut windows);
+        Ok(windows)
+    }
+}
+
+fn find_focused(tree: &Value) -> Option<WindowInformation> {
+    let mut windows = Vec::new();
+    find_windows(tree, None, None, true, &mut windows);
+    windows.into_iter().next()
+}
+
+/// Depth-first search of the windows, keeping track of the output and workspace on the way.
+fn find_windows(
+    node: &Value,
+    output: Option<&Value>,
+    workspace: Option<&Value>,
+    focused_only: bool,
+    windows: &mut Vec<WindowInformation>,
+) {
+    let (output, workspace) = match node["type"].as_str() {
+        Some("output") => (Some(node), workspace),
+        Some("workspace") => (output, Some(node)),
+        _ => (output, workspace),
+    };
+    // Workspaces, outputs and split containers can get the focus too, they aren't windows
+    let is_window = (node["type"] == "con" || node["type"] == "floating_con")
+        && (node["app_id"].is_string() || node["window_properties"].is_object());
+    if is_window && (!focused_only || node["focused"] == Value::Bool(true)) {
+        windows.push(window_from_node(node, output, workspace));
+        return;
+    }
+
+    for child in node["nodes"]
+        .as_array()
+        .into_iter()
+        .chain(node["floating_nodes"].as_array())
+        .flatten()
+    {
+        find_windows(child, output, workspace, focused_only, windows);
+    }
+}
+
+fn window_from_node(
+    node: &Value,
+    output: Option<&Value>,
+    workspace: Option<&Value>,
+) -> WindowInformation {
+    let time = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+
+    // Same order as WM_CLASS on X11: instance, then class
+    let properties = &node["window_properties"];
+    let class = match node["app_id"].as_str() {
+        Some(app_id) => vec![app_id.to_string()],
+        None => [&properties["instance"], &properties["class"]]
+            .into_iter()
+            .filter_map(|v| v.as_str().map(str::to_string))
+            .collect(),
+    };
+
+    let pid = node["pid"].as_u64().map(|pid| pid as u32);
+    let execpath = pid.and_then(get_exec_path);
+
+    WindowInformation {
+        time,
+        title: node["name"].as_str().map(str::to_string),
+        class,
+        execpath,
+        pid,
+        id: node["id"].as_u64(),
+        workspace: workspace.map(|w| Workspace {
+            // Named workspaces have no number, reported as -1 by i3
+            index: w["num"].as_i64().and_then(|n| u32::try_from(n).ok()),
+            name: w["name"].as_str().map(str::to_string),
+        }),
+        output: output.and_then(|o| o["name"].as_str()).map(str::to_string),
+        geometry: parse_rect(&node["rect"]),
+        // False for windows in hidden tabs and on workspaces not shown
+        visible: node["visible"].as_bool(),
+        ..Default::default()
+    }
+}
+
+/// `rect` is the absolute position of the container, decorations included.
+fn parse_rect(rect: &Value) -> Option<Geometry> {
+    Some(Geometry {
+        x: rect["x"].as_i64()? as i32,
+        y: rect["y"].as_i64()? as i32,
+        width: rect["width"].as_u64()? as u32,
+        height: rect["height"].as_u64()? as u32,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+    use std::os::unix::net::UnixListener;
+
+    // Trimmed from `swaymsg -t get_tree -r`
+    const TREE: &str = r#"{
+        "id": 1, "type": "root", "name": "root", "focused": false,
+        "nodes": [
+            {"id": 2147483646, "type": "output", "name": "__i3", "focused": false, "nodes": []},
+            {"id": 3, "type": "output", "name": "eDP-1", "focused": false, "nodes": [
+                {"id": 4, "type": "workspace", "name": "1", "num": 1, "focused": false,
+                 "nodes": [
+                    {"id": 7, "type": "con", "name": "Mozilla Firefox", "focused": false,
+                     "app_id": "firefox", "pid": 4001,
+                     "rect": {"x": 0, "y": 0, "width": 960, "height": 1080}}
+                 ],
+                 "floating_nodes": []},
+                {"id": 5, "type": "workspace", "name": "mail", "num": -1, "focused": false,
+                 "nodes": [
+                    {"id": 8, "type": "con", "name": null, "focused": false, "nodes": [
+                        {"id": 9, "type": "con", "name": "Inbox - Thunderbird", "focused": true,
+                         "app_id": null, "pid": 4002,
+                         "window_properties": {"class": "thunderbird", "instance": "Mail"},
+                         "rect": {"x": 960, "y": 24, "width": 960, "height": 1056}}
+                    ]}
+                 ],
+                 "floating_nodes": []}
+            ]}
+        ]
+    }"#;
+
+    fn message(kind: u32, payload: &str) -> Vec<u8> {
+        let mut message = MAGIC.to_vec();
+        message.extend_from_slice(&(payload.len() as u32).to_ne_bytes());
+        message.extend_from_slice(&kind.to_ne_bytes());
+        message.extend_from_slice(payload.as_bytes());
+        message
+    }
+
+    /// Socket path in a directory of its own, removed with the returned `TempDir`.
+    fn test_socket(name: &str) -> (TempDir, PathBuf) {
+        let dir = TempDir::new(&format!("sway-{}", name));
+        let path = dir.path().join("sway.sock");
+        (dir, path)
+    }
+
+    /// Answer GET_TREE requests with TREE until the client hangs up.
+    fn serve_tree(listener: UnixListener) {
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut header = [0u8; 14];
+            while stream.read_exact(&mut header).is_ok() {
+                assert_eq!(&header[..6], MAGIC);
+                assert_eq!(
+                    u32::from_ne_bytes(header[10..].try_into().unwrap()),
+                    GET_TREE
+                );
+                stream.write_all(&message(GET_TREE, TREE)).unwrap();
+            }
+        });
+    }
+
+    #[test]
+    fn test_get_focused_window() {
+        let (_dir, path) = test_socket("tree");
+        serve_tree(UnixListener::bind(&path).unwrap());
+
+        let window = IpcConnection::connect(&path)
+            .unwrap()
+            .get_focused_window()
+            .unwrap()
+            .unwrap();
+        assert_eq!(window.title.as_deref(), Some("Inbox - Thunderbird"));
+        assert_eq!(window.class, vec!["Mail", "thunderbird"]);
+        assert_eq!(window.pid, Some(4002));
+        assert_eq!(window.id, Some(9));
+        assert_eq!(window.output.as_deref(), Some("eDP-1"));
+        assert_eq!(
+            window.workspace,
+            Some(Workspace {
+                index: None,
+                name: Some("mail".to_string())
+            })
+        );
+        assert_eq!(
+            window.geometry,
+            Some(Geometry {
+                x: 960,
+                y: 24,
+                width: 960,
+                height: 1056
+            })
+        );
+    }
+
+    #[test]
+    fn test_list_windows() {
+        let (_dir, path) = test_socket("list");
+        serve_tree(UnixListener::bind(&path).unwrap());
+
+        let windows = IpcConnection::connect(&path)
+            .unwrap()
+            .list_windows()
+            .unwrap();
+        let titles: Vec<_> = windows.iter().map(|w| w.title.as_deref()).collect();
+        assert_eq!(
+            titles,
+            vec![Some("Mozilla Firefox"), Some("Inbox - Thunderbird")]
+        );
+        assert_eq!(windows[0].workspace.as_ref().unwrap().index, Some(1));
+    }
+
+    #[test]
+    fn test_focus_events() {
+        let (_dir, path) = test_socket("events");
+        let listener = UnixListener::bind(&path).unwrap();
+        let events = IpcConnection::connect(&path).unwrap();
+        let (mut stream, _) = listener.accept().unwrap();
+        serve_tree(listener);
+
+        let (tx, rx) = mpsc::channel();
+        let watch_path = path.clone();
+        thread::spawn(move || watch(events, &watch_path, tx));
+
+        let mut header = [0u8; 14];
+        stream.read_exact(&mut header).unwrap();
+        let len = u32::from_ne_bytes(header[6..10].try_into().unwrap());
+        let mut payload = vec![0u8; len as usize];
+        stream.read_exact(&mut payload).unwrap();
+        assert_eq!(payload, br#"["window"]"#);
+        stream
+            .write_all(&message(SUBSCRIBE, r#"{"success": true}"#))
+            .unwrap();
+
+        // Title change of an unfocused window, then a focus change
+        stream
+            .write_all(&message(
+                EVENT_WINDOW,
+                r#"{"change": "title", "container": {"id": 7, "focused": false}}"#,
+            ))
+            .unwrap();
+        stream
+            .write_all(&message(
+                EVENT_WINDOW,
+                r#"{"change": "focus", "container": {"id": 9, "focused": true}}"#,
+            ))
+            .unwrap();
+
+        match rx.recv().unwrap() {
+            WindowEvent::FocusChanged(window) => assert_eq!(window.id, Some(9)),
+            event => panic!("Unexpected event {:?}", event),
+        }
+    }
+}
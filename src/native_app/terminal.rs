@@ -0,0 +1,137 @@
+// Foreground program of a terminal emulator window. The terminal owns the master side of one
+// PTY per tab, and forks a shell on each slave side. The shells are the terminal's children
+// with a controlling terminal, and field 8 of their /proc/<pid>/stat (tpgid) is the process
+// group in the foreground of their PTY, whose leader is the running command (vim, cargo, ssh)
+// or the shell itself at the prompt.
+// With several tabs, the focused one is guessed as the PTY written to last: its mtime moves on
+// each input and output.
+#![cfg(target_os = "linux")]
+use {
+    super::process::{get_process_info, read_stat},
+    crate::types::ProcessInfo,
+    std::{fs, path::Path, time::SystemTime},
+};
+
+/// Executables of terminal emulators, as found in argv[0] or argv[1] for the ones written
+/// in Python.
+const TERMINALS: &[&str] = &[
+    "gnome-terminal-server",
+    "kgx",
+    "ptyxis-agent",
+    "kitty",
+    "alacritty",
+    "wezterm-gui",
+    "konsole",
+    "yakuake",
+    "xterm",
+    "uxterm",
+    "urxvt",
+    "foot",
+    "tilix",
+    "terminator",
+    "xfce4-terminal",
+    "mate-terminal",
+    "lxterminal",
+    "qterminal",
+    "terminology",
+    "ghostty",
+    "st",
+];
+
+pub fn is_terminal_emulator(argv: &[String]) -> bool {
+    argv.iter().take(2).any(|arg| {
+        let name = Path::new(arg).file_name().and_then(|name| name.to_str());
+        name.is_some_and(|name| TERMINALS.contains(&name))
+    })
+}
+
+/// Process in the foreground of the most recently used PTY of `terminal_pid`.
+/// `None` when the terminal has no shell.
+pub fn get_foreground_process(terminal_pid: u32) -> Option<ProcessInfo> {
+    let shell = children(terminal_pid)
+        .into_iter()
+        .filter_map(|pid| Some((pid, read_stat(pid)?)))
+        .filter(|(_, stat)| stat.tpgid > 0)
+        .max_by_key(|(pid, _)| pty_modified(*pid))?;
+    let (shell_pid, stat) = shell;
+    // The group leader may have exited while other members of the group still run
+    get_process_info(stat.tpgid as u32).or_else(|| get_process_info(shell_pid))
+}
+
+fn children(pid: u32) -> Vec<u32> {
+    let Ok(entries) = fs::read_dir("/proc") else {
+        return Vec::new();
+    };
+    entries
+        .filter_map(|entry| entry.ok()?.file_name().to_str()?.parse::<u32>().ok())
+        .filter(|child| read_stat(*child).is_some_and(|stat| stat.ppid == pid))
+        .collect()
+}
+
+/// Last time the PTY of a shell was read or written, through its stdin.
+fn pty_modified(pid: u32) -> Option<SystemTime> {
+    let pty = fs::read_link(format!("/proc/{}/fd/0", pid)).ok()?;
+    fs::metadata(pty).ok()?.modified().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{
+        process::{Command, Stdio},
+        thread::sleep,
+        time::{Duration, Instant},
+    };
+
+    #[test]
+    fn test_is_terminal_emulator() {
+        let argv = |args: &[&str]| args.iter().map(|a| a.to_string()).collect::<Vec<_>>();
+        assert!(is_terminal_emulator(&argv(&[
+            "/usr/libexec/gnome-terminal-server"
+        ])));
+        assert!(is_terminal_emulator(&argv(&[
+            "/usr/bin/python3",
+            "/usr/bin/terminator"
+        ])));
+        assert!(!is_terminal_emulator(&argv(&[
+            "/usr/bin/code",
+            "--new-window"
+        ])));
+    }
+
+    #[test]
+    fn test_get_foreground_process() {
+        // script(1) plays the terminal: it runs a shell on a new PTY, with job control for sleep
+        // to get a foreground process group of its own
+        let Ok(mut terminal) = Command::new("script")
+            .args(["-q", "-c", "set -m; sleep 30; true", "/dev/null"])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .spawn()
+        else {
+            eprintln!("script not available, skipping");
+            return;
+        };
+        // The shell is in the foreground until it has started sleep
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let foreground = loop {
+            let foreground = get_foreground_process(terminal.id());
+            let sleeping = foreground
+                .as_ref()
+                .is_some_and(|f| f.argv.first().is_some_and(|a| a == "sleep"));
+            if sleeping || Instant::now() > deadline {
+                break foreground;
+            }
+            sleep(Duration::from_millis(20));
+        };
+        terminal.kill().unwrap();
+        terminal.wait().unwrap();
+
+        let foreground = foreground.expect("No foreground process found within 5s");
+        assert_eq!(foreground.argv, vec!["sleep", "30"]);
+        assert_eq!(
+            foreground.cwd,
+            Some(std::env::current_dir().unwrap().display().to_string())
+        );
+    }
+}
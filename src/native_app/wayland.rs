@@ -0,0 +1,357 @@
+// Wayland backend for wlroots-based compositors (sway, river, Hyprland, labwc, Wayfire, ...).
+// Native Wayland clients are invisible to X11, so the focused window is taken from the
+// toplevel list the compositor exposes:
+// - zwlr_foreign_toplevel_manager_v1: title, app_id and state (activated) of every toplevel
+// - ext_foreign_toplevel_list_v1 (where available): title, app_id and a stable identifier,
+//   without any state, so it is only used to complete the wlr data
+// The protocols don't expose PIDs, so `pid` and `execpath` stay `None`.
+#![cfg(target_os = "linux")]
+use {
+    super::WindowBackend,
+    crate::{InspectError, WindowInformation},
+    anyhow::{anyhow, Result},
+    std::collections::HashMap,
+    std::sync::Mutex,
+    std::time::SystemTime,
+    wayland_client::{
+        backend::ObjectId,
+        event_created_child,
+        globals::{registry_queue_init, GlobalListContents},
+        protocol::wl_registry,
+        Connection, Dispatch, EventQueue, Proxy, QueueHandle,
+    },
+    wayland_protocols::ext::foreign_toplevel_list::v1::client::{
+        ext_foreign_toplevel_handle_v1::{self, ExtForeignToplevelHandleV1},
+        ext_foreign_toplevel_list_v1::{self, ExtForeignToplevelListV1},
+    },
+    wayland_protocols_wlr::foreign_toplevel::v1::client::{
+        zwlr_foreign_toplevel_handle_v1::{self, ZwlrForeignToplevelHandleV1},
+        zwlr_foreign_toplevel_manager_v1::{self, ZwlrForeignToplevelManagerV1},
+    },
+};
+
+#[derive(Debug, Default, Clone)]
+struct Toplevel {
+    title: String,
+    app_id: String,
+    activated: bool,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    /// Toplevels announced by zwlr_foreign_toplevel_manager_v1
+    toplevels: HashMap<ObjectId, Toplevel>,
+    /// Toplevels announced by ext_foreign_toplevel_list_v1
+    listed: HashMap<ObjectId, Toplevel>,
+    has_wlr_manager: bool,
+}
+
+struct WaylandSession {
+    // Kept alive for the lifetime of the session
+    _conn: Connection,
+    queue: EventQueue<State>,
+    state: State,
+}
+
+/// Connection shared by every call. Reset when a roundtrip fails, e.g. after the compositor exited.
+static SESSION: Mutex<Option<WaylandSession>> = Mutex::new(None);
+
+pub struct WlrBackend;
+
+impl WindowBackend for WlrBackend {
+    fn name(&self) -> &'static str {
+        "wlr"
+    }
+
+    fn current(&self) -> Result<Option<WindowInformation>> {
+        get_current_window_information()
+    }
+
+    fn list_windows(&self) -> Result<Vec<WindowInformation>> {
+        with_session(WaylandSession::get_toplevels)
+    }
+}
+
+/// Focused toplevel. Fails if the compositor doesn't support the wlr toplevel protocol,
+/// so the caller can fall back to another backend.
+pub fn get_current_window_information() -> Result<Option<WindowInformation>> {
+    with_session(WaylandSession::get_active_toplevel)
+}
+
+fn with_session<T>(f: impl FnOnce(&mut WaylandSession) -> Result<T>) -> Result<T> {
+    let mut session = SESSION
+        .lock()
+        .map_err(|_| anyhow!("Wayland session poisoned"))?;
+    if session.is_none() {
+        *session = Some(WaylandSession::connect()?);
+    }
+
+    let result = session.as_mut().map(f);
+    match result {
+        Some(Ok(value)) => Ok(value),
+        Some(Err(e)) => {
+            *session = None;
+            Err(e)
+        }
+        None => Err(anyhow!("Wayland session unavailable")),
+    }
+}
+
+impl WaylandSession {
+    fn connect() -> Result<Self> {
+        let conn = Connection::connect_to_env().map_err(|e| {
+            log::debug!("Cannot connect to the compositor: {}", e);
+            InspectError::NoDisplay
+        })?;
+        let (globals, mut queue) = registry_queue_init::<State>(&conn)?;
+        let qh = queue.handle();
+        let mut state = State::default();
+
+        let wlr = globals.bind::<ZwlrForeignToplevelManagerV1, _, _>(&qh, 1..=3, ());
+        state.has_wlr_manager = wlr.is_ok();
+        if globals
+            .bind::<ExtForeignToplevelListV1, _, _>(&qh, 1..=1, ())
+            .is_err()
+        {
+            log::debug!("ext_foreign_toplevel_list_v1 is not available");
+        }
+        if !state.has_wlr_manager {
+            return Err(anyhow!(
+                "Compositor doesn't support zwlr_foreign_toplevel_manager_v1"
+            ));
+        }
+
+        // Receive the initial list of toplevels and their properties
+        queue.roundtrip(&mut state)?;
+        Ok(WaylandSession {
+            _conn: conn,
+            queue,
+            state,
+        })
+    }
+
+    fn get_active_toplevel(&mut self) -> Result<Option<WindowInformation>> {
+        self.queue.roundtrip(&mut self.state)?;
+        Ok(self.state.active_toplevel().map(|t| t.to_window()))
+    }
+
+    fn get_toplevels(&mut self) -> Result<Vec<WindowInformation>> {
+        self.queue.roundtrip(&mut self.state)?;
+        Ok(self
+            .state
+            .toplevels
+            .values()
+            .map(Toplevel::to_window)
+            .collect())
+    }
+}
+
+impl Toplevel {
+    fn to_window(&self) -> WindowInformation {
+        let time = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        WindowInformation {
+            time,
+            title: Some(self.title.clone()).filter(|t| !t.is_empty()),
+            class: Some(self.app_id.clone())
+                .filter(|a| !a.is_empty())
+                .into_iter()
+                .collect(),
+            ..Default::default()
+        }
+    }
+}
+
+impl State {
+    fn active_toplevel(&self) -> Option<Toplevel> {
+        let toplevel = self.toplevels.values().find(|t| t.activated)?.clone();
+        Some(merge_listed(toplevel, self.listed.values()))
+    }
+}
+
+/// Some compositors only fill the app_id in the ext list. The two lists share no identifier, so
+/// the ext entries are matched by title, and the app_id is left empty when entries with that
+/// title disagree on it.
+fn merge_listed<'a>(
+    mut toplevel: Toplevel,
+    listed: impl IntoIterator<Item = &'a Toplevel>,
+) -> Toplevel {
+    if !toplevel.app_id.is_empty() {
+        return toplevel;
+    }
+    let mut app_ids = listed
+        .into_iter()
+        .filter(|l| l.title == toplevel.title && !l.app_id.is_empty())
+        .map(|l| &l.app_id);
+    if let Some(first) = app_ids.next() {
+        if app_ids.all(|a| a == first) {
+            toplevel.app_id = first.clone();
+        }
+    }
+    toplevel
+}
+
+/// The wlr state event carries an array of u32 states in native endianness.
+fn is_activated(state: &[u8]) -> bool {
+    let activated = zwlr_foreign_toplevel_handle_v1::State::Activated as u32;
+    state
+        .chunks_exact(4)
+        .any(|c| u32::from_ne_bytes([c[0], c[1], c[2], c[3]]) == activated)
+}
+
+impl Dispatch<wl_registry::WlRegistry, GlobalListContents> for State {
+    fn event(
+        _: &mut Self,
+        _: &wl_registry::WlRegistry,
+        _: wl_registry::Event,
+        _: &GlobalListContents,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+    }
+}
+
+impl Dispatch<ZwlrForeignToplevelManagerV1, ()> for State {
+    fn event(
+        state: &mut Self,
+        _: &ZwlrForeignToplevelManagerV1,
+        event: zwlr_foreign_toplevel_manager_v1::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        match event {
+            zwlr_foreign_toplevel_manager_v1::Event::Toplevel { toplevel } => {
+                state.toplevels.insert(toplevel.id(), Toplevel::default());
+            }
+            zwlr_foreign_toplevel_manager_v1::Event::Finished => {
+                state.has_wlr_manager = false;
+                state.toplevels.clear();
+            }
+            _ => {}
+        }
+    }
+
+    event_created_child!(State, ZwlrForeignToplevelManagerV1, [
+        zwlr_foreign_toplevel_manager_v1::EVT_TOPLEVEL_OPCODE => (ZwlrForeignToplevelHandleV1, ()),
+    ]);
+}
+
+impl Dispatch<ZwlrForeignToplevelHandleV1, ()> for State {
+    fn event(
+        state: &mut Self,
+        handle: &ZwlrForeignToplevelHandleV1,
+        event: zwlr_foreign_toplevel_handle_v1::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        if let zwlr_foreign_toplevel_handle_v1::Event::Closed = event {
+            state.toplevels.remove(&handle.id());
+            handle.destroy();
+            return;
+        }
+        let toplevel = state.toplevels.entry(handle.id()).or_default();
+        match event {
+            zwlr_foreign_toplevel_handle_v1::Event::Title { title } => toplevel.title = title,
+            zwlr_foreign_toplevel_handle_v1::Event::AppId { app_id } => toplevel.app_id = app_id,
+            zwlr_foreign_toplevel_handle_v1::Event::State { state } => {
+                toplevel.activated = is_activated(&state)
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Dispatch<ExtForeignToplevelListV1, ()> for State {
+    fn event(
+        state: &mut Self,
+        _: &ExtForeignToplevelListV1,
+        event: ext_foreign_toplevel_list_v1::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        match event {
+            ext_foreign_toplevel_list_v1::Event::Toplevel { toplevel } => {
+                state.listed.insert(toplevel.id(), Toplevel::default());
+            }
+            ext_foreign_toplevel_list_v1::Event::Finished => state.listed.clear(),
+            _ => {}
+        }
+    }
+
+    event_created_child!(State, ExtForeignToplevelListV1, [
+        ext_foreign_toplevel_list_v1::EVT_TOPLEVEL_OPCODE => (ExtForeignToplevelHandleV1, ()),
+    ]);
+}
+
+impl Dispatch<ExtForeignToplevelHandleV1, ()> for State {
+    fn event(
+        state: &mut Self,
+        handle: &ExtForeignToplevelHandleV1,
+        event: ext_foreign_toplevel_handle_v1::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        if let ext_foreign_toplevel_handle_v1::Event::Closed = event {
+            state.listed.remove(&handle.id());
+            handle.destroy();
+            return;
+        }
+        let toplevel = state.listed.entry(handle.id()).or_default();
+        match event {
+            ext_foreign_toplevel_handle_v1::Event::Title { title } => toplevel.title = title,
+            ext_foreign_toplevel_handle_v1::Event::AppId { app_id } => toplevel.app_id = app_id,
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn states(values: &[u32]) -> Vec<u8> {
+        values.iter().flat_map(|v| v.to_ne_bytes()).collect()
+    }
+
+    #[test]
+    fn test_is_activated() {
+        // maximized (0), activated (2)
+        assert!(is_activated(&states(&[0, 2])));
+        // minimized (1), fullscreen (3)
+        assert!(!is_activated(&states(&[1, 3])));
+        assert!(!is_activated(&[]));
+    }
+
+    #[test]
+    fn test_merge_listed() {
+        let toplevel = |title: &str, app_id: &str| Toplevel {
+            title: title.to_string(),
+            app_id: app_id.to_string(),
+            activated: false,
+        };
+        let listed = [
+            toplevel("notes.md - Kate", "org.kde.kate"),
+            toplevel("Terminal", "foot"),
+            toplevel("Terminal", "Alacritty"),
+            toplevel("~", "foot"),
+            toplevel("~", "foot"),
+        ];
+        let merged =
+            |title: &str, app_id: &str| merge_listed(toplevel(title, app_id), &listed).app_id;
+        assert_eq!(merged("notes.md - Kate", ""), "org.kde.kate");
+        // Already known
+        assert_eq!(merged("notes.md - Kate", "kate"), "kate");
+        // Not listed
+        assert_eq!(merged("Untitled", ""), "");
+        // Same title, different apps
+        assert_eq!(merged("Terminal", ""), "");
+        // Same title, same app
+        assert_eq!(merged("~", ""), "foot");
+    }
+}
@@ -20,10 +20,10 @@ pub fn get_current_window_information() -> Option<WindowInformation> {
     let title: String;
     let name: String;
     let execpath: String;
+    let mut pid = 0;
+    let hwnd = unsafe { GetForegroundWindow() };
 
     unsafe {
-        let mut pid = 0;
-        let hwnd = GetForegroundWindow();
         GetWindowThreadProcessId(hwnd, Option::Some(&mut pid));
         title = get_window_title(hwnd).unwrap();
 
@@ -35,10 +35,12 @@ pub fn get_current_window_information() -> Option<WindowInformation> {
     }
     Some(WindowInformation {
         time: unix_ts.as_secs(),
-        title,
+        title: Some(title),
         class: vec![name],
-        execpath,
-        url: None,
+        execpath: Some(execpath),
+        pid: Some(pid),
+        id: Some(hwnd.0 as usize as u64),
+        ..Default::default()
     })
 }
 
@@ -86,3 +88,17 @@ fn get_window_title(hwnd: HWND) -> Option<String> {
         Some("".to_string())
     }
 }
+
+#[cfg(target_os = "windows")]
+pub struct WindowsBackend;
+
+#[cfg(target_os = "windows")]
+impl super::WindowBackend for WindowsBackend {
+    fn name(&self) -> &'static str {
+        "windows"
+    }
+
+    fn current(&self) -> anyhow::Result<Option<WindowInformation>> {
+        Ok(get_current_window_information())
+    }
+}
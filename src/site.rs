@@ -0,0 +1,207 @@
+// Normalize browser URLs so reports and rules can group activity by site.
+// The registrable domain (eTLD+1) comes from the public suffix list embedded by the `psl` crate,
+// so `mail.google.com` and `docs.google.com` both group under `google.com`,
+// while `alice.github.io` and `bob.github.io` stay apart.
+use {
+    crate::types::{BrowserTab, WindowInformation},
+    url::{Host, Url},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UrlKind {
+    /// http(s) page
+    Web,
+    /// Browser UI page: `chrome://`, `edge://`, `about:`, extension pages, ...
+    BrowserInternal,
+    /// Local file opened in the browser
+    File,
+    /// `view-source:` of another URL. Host and domain are the ones of the viewed URL.
+    ViewSource,
+    /// Any other scheme (ftp, data, javascript, ...)
+    Other,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NormalizedUrl {
+    pub kind: UrlKind,
+    /// Lower-cased scheme. For `view-source:` URLs, the scheme of the viewed URL.
+    pub scheme: String,
+    /// Lower-cased host in ASCII (punycode) form. `None` for `about:` and `file://` URLs.
+    pub host: Option<String>,
+    /// Host with punycode labels decoded, for display.
+    pub host_unicode: Option<String>,
+    /// eTLD+1 in ASCII form, e.g. `example.co.uk`. `None` for IP addresses and
+    /// hosts that are themselves a public suffix (`localhost`, `github.io`).
+    pub registrable_domain: Option<String>,
+    pub port: Option<u16>,
+    pub path: String,
+    /// The URL without credentials, query string and fragment.
+    pub redacted: String,
+}
+
+const INTERNAL_SCHEMES: &[&str] = &[
+    "about",
+    "chrome",
+    "chrome-extension",
+    "chrome-search",
+    "chrome-untrusted",
+    "devtools",
+    "edge",
+    "brave",
+    "opera",
+    "vivaldi",
+    "moz-extension",
+    "resource",
+    "safari-extension",
+];
+
+/// Parse and normalize a URL. Address bar text without a scheme (`example.com/path`) is
+/// treated as https, as browsers do.
+pub fn normalize_url(raw: &str) -> Option<NormalizedUrl> {
+    let raw = raw.trim();
+    if let Some(inner) = raw.strip_prefix("view-source:") {
+        let mut normalized = normalize_url(inner)?;
+        normalized.kind = UrlKind::ViewSource;
+        normalized.redacted = format!("view-source:{}", normalized.redacted);
+        return Some(normalized);
+    }
+
+    let url = match Url::parse(raw) {
+        // "localhost:3000/app" parses as scheme "localhost" with path "3000/app"
+        Ok(url)
+            if url.cannot_be_a_base() && url.path().starts_with(|c: char| c.is_ascii_digit()) =>
+        {
+            Url::parse(&format!("https://{}", raw)).ok()?
+        }
+        Ok(url) => url,
+        Err(url::ParseError::RelativeUrlWithoutBase) => {
+            Url::parse(&format!("https://{}", raw)).ok()?
+        }
+        Err(_) => return None,
+    };
+
+    let scheme = url.scheme().to_string();
+    let kind = match scheme.as_str() {
+        "http" | "https" => UrlKind::Web,
+        "file" => UrlKind::File,
+        s if INTERNAL_SCHEMES.contains(&s) => UrlKind::BrowserInternal,
+        _ => UrlKind::Other,
+    };
+
+    let host = url
+        .host_str()
+        .filter(|h| !h.is_empty())
+        .map(str::to_lowercase);
+    let host_unicode = host.as_ref().map(|h| idna::domain_to_unicode(h).0);
+    let registrable_domain = match (kind, url.host()) {
+        (UrlKind::Web, Some(Host::Domain(domain))) => {
+            psl::domain_str(&domain.to_lowercase()).map(str::to_string)
+        }
+        _ => None,
+    };
+
+    let mut redacted = url.clone();
+    redacted.set_query(None);
+    redacted.set_fragment(None);
+    let _ = redacted.set_username("");
+    let _ = redacted.set_password(None);
+
+    Some(NormalizedUrl {
+        kind,
+        scheme,
+        host,
+        host_unicode,
+        registrable_domain,
+        port: url.port(),
+        path: url.path().to_string(),
+        redacted: redacted.to_string(),
+    })
+}
+
+impl BrowserTab {
+    pub fn normalized_url(&self) -> Option<NormalizedUrl> {
+        normalize_url(&self.url)
+    }
+}
+
+impl WindowInformation {
+    /// Normalized URL of the active browser tab, if the window is a browser.
+    pub fn normalized_url(&self) -> Option<NormalizedUrl> {
+        normalize_url(self.url.as_ref()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_web_url() {
+        let url =
+            normalize_url("https://user:pw@Mail.Google.com:8443/mail/u/0?tab=1#inbox").unwrap();
+        assert_eq!(url.kind, UrlKind::Web);
+        assert_eq!(url.scheme, "https");
+        assert_eq!(url.host.as_deref(), Some("mail.google.com"));
+        assert_eq!(url.registrable_domain.as_deref(), Some("google.com"));
+        assert_eq!(url.port, Some(8443));
+        assert_eq!(url.path, "/mail/u/0");
+        assert_eq!(url.redacted, "https://mail.google.com:8443/mail/u/0");
+    }
+
+    #[test]
+    fn test_public_suffixes() {
+        let domain = |u: &str| normalize_url(u).unwrap().registrable_domain;
+        assert_eq!(
+            domain("https://www.bbc.co.uk/news").as_deref(),
+            Some("bbc.co.uk")
+        );
+        assert_eq!(
+            domain("https://alice.github.io/").as_deref(),
+            Some("alice.github.io")
+        );
+        assert_eq!(domain("http://localhost:3000/"), None);
+        assert_eq!(domain("http://192.168.1.1/admin"), None);
+    }
+
+    #[test]
+    fn test_idn() {
+        let url = normalize_url("https://Bücher.example.de/").unwrap();
+        assert_eq!(url.host.as_deref(), Some("xn--bcher-kva.example.de"));
+        assert_eq!(url.host_unicode.as_deref(), Some("bücher.example.de"));
+        assert_eq!(url.registrable_domain.as_deref(), Some("example.de"));
+    }
+
+    #[test]
+    fn test_special_urls() {
+        let url = normalize_url("chrome://settings/privacy").unwrap();
+        assert_eq!(url.kind, UrlKind::BrowserInternal);
+        assert_eq!(url.host.as_deref(), Some("settings"));
+        assert!(url.registrable_domain.is_none());
+
+        assert_eq!(
+            normalize_url("about:blank").unwrap().kind,
+            UrlKind::BrowserInternal
+        );
+
+        let url = normalize_url("file:///home/me/report.html?x=1").unwrap();
+        assert_eq!(url.kind, UrlKind::File);
+        assert_eq!(url.path, "/home/me/report.html");
+        assert_eq!(url.redacted, "file:///home/me/report.html");
+
+        let url = normalize_url("view-source:https://docs.rs/serde?search=x").unwrap();
+        assert_eq!(url.kind, UrlKind::ViewSource);
+        assert_eq!(url.registrable_domain.as_deref(), Some("docs.rs"));
+        assert_eq!(url.redacted, "view-source:https://docs.rs/serde");
+    }
+
+    #[test]
+    fn test_address_bar_text_without_scheme() {
+        let url = normalize_url("example.com/path?q=1").unwrap();
+        assert_eq!(url.scheme, "https");
+        assert_eq!(url.registrable_domain.as_deref(), Some("example.com"));
+
+        let url = normalize_url("localhost:3000/app").unwrap();
+        assert_eq!(url.host.as_deref(), Some("localhost"));
+        assert_eq!(url.port, Some(3000));
+    }
+}
@@ -0,0 +1,69 @@
+// Helpers shared by the tests of several modules.
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+#[cfg(target_os = "linux")]
+use std::{
+    io::{BufRead, BufReader},
+    process::{Child, Command, Stdio},
+};
+
+/// Private bus, killed when dropped.
+#[cfg(target_os = "linux")]
+pub struct DbusDaemon(Child);
+
+#[cfg(target_os = "linux")]
+impl Drop for DbusDaemon {
+    fn drop(&mut self) {
+        let _ = self.0.kill();
+        let _ = self.0.wait();
+    }
+}
+
+/// Start a session bus of our own. `None` when dbus-daemon isn't installed.
+#[cfg(target_os = "linux")]
+pub fn start_dbus_daemon() -> Option<(DbusDaemon, String)> {
+    let mut child = Command::new("dbus-daemon")
+        .args(["--session", "--nofork", "--print-address"])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .ok()?;
+    let mut address = String::new();
+    BufReader::new(child.stdout.take()?)
+        .read_line(&mut address)
+        .ok()?;
+    Some((DbusDaemon(child), address.trim().to_string()))
+}
+
+/// Directory of the system temp dir, removed with its content when dropped.
+pub struct TempDir(PathBuf);
+
+impl TempDir {
+    /// Empty directory, named after `name` and the test process.
+    pub fn new(name: &str) -> TempDir {
+        let path =
+            std::env::temp_dir().join(format!("screen-inspector-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).unwrap();
+        TempDir(path)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+
+    /// Write `content` to `file`, relative to the directory, creating its parents.
+    pub fn write(&self, file: &str, content: &str) {
+        let path = self.0.join(file);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
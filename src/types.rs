@@ -1,16 +1,244 @@
-#[derive(Debug)]
+use crate::browser::{
+    get_desktop_id, get_process_name, identify, BrowserIdentification, IdentificationInput,
+};
+
+#[derive(Debug, Clone, Default)]
 pub struct WindowInformation {
     pub time: u64,
-    pub title: String,
+    /// `None` when the window has no title at all, as some Java and Wine windows.
+    pub title: Option<String>,
     pub class: Vec<String>,
-    pub execpath: String,
+    /// Path of the executable owning the window. `None` when the PID is unknown.
+    pub execpath: Option<String>,
+    /// PID of the process owning the window, if the platform reports it.
+    pub pid: Option<u32>,
+    /// Command line, user, parents, ... of the process owning the window. Linux only.
+    pub process: Option<ProcessInfo>,
+    /// For terminal emulators, the program in the foreground of the active tab, e.g. vim at
+    /// the path being edited. The shell itself when it waits at the prompt.
+    pub foreground_process: Option<ProcessInfo>,
+    /// Active pane when `foreground_process` is a tmux or GNU screen client.
+    pub pane: Option<TerminalPane>,
+    /// Freedesktop desktop entry of the application, for its name, icon and categories.
+    pub desktop_entry: Option<DesktopEntry>,
+    /// Host of the ssh, mosh, RDP or VNC session shown in the window.
+    pub remote_target: Option<RemoteTarget>,
+    /// Native window id (X11 window id, HWND, sway container id).
+    pub id: Option<u64>,
+    /// Workspace (virtual desktop) the window is on. The current one for windows shown on
+    /// every workspace.
+    pub workspace: Option<Workspace>,
+    /// Name of the output (monitor) the window is on, e.g. "eDP-1".
+    pub output: Option<String>,
+    /// Absolute position and size of the window.
+    pub geometry: Option<Geometry>,
+    /// Fullscreen, maximized, ... flags, if the backend reports them.
+    pub state: Option<WindowState>,
+    /// Position in the stacking order from `list_windows`, 0 at the bottom.
+    pub z_order: Option<u32>,
+    /// Shown on the current desktop, i.e. neither minimized nor on another workspace. It may
+    /// still be covered by the windows above it.
+    pub visible: Option<bool>,
+    /// Whether the window is an X11 client running under XWayland.
+    pub xwayland: Option<bool>,
+    /// KDE Plasma activity the window belongs to.
+    pub activity: Option<String>,
+    /// `_NET_WM_WINDOW_TYPE` on X11.
+    pub window_type: Option<WindowType>,
+    /// `WM_WINDOW_ROLE` on X11, e.g. "browser" or "pop-up".
+    pub role: Option<String>,
+    /// `_GTK_APPLICATION_ID` of GTK applications on X11, e.g. "org.gnome.Nautilus".
+    pub application_id: Option<String>,
+    /// Host the X client runs on (`WM_CLIENT_MACHINE`).
+    pub client_machine: Option<String>,
+    /// Whether the client runs on another host, e.g. through `ssh -X` or a VDI session.
+    pub remote: Option<bool>,
     /// URL of the active tab in the browser. Only set if the window is a browser window.
+    /// Same as `tab.url`, kept for consumers that only need the URL.
     pub url: Option<String>,
+    /// Active tab in the browser, with everything the URL source knows about it.
+    pub tab: Option<BrowserTab>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Workspace {
+    /// Workspace number as the backend counts: from 0 on X11, GNOME and KWin, the workspace
+    /// number on sway and Hyprland. Not every workspace has one, e.g. named sway workspaces.
+    pub index: Option<u32>,
+    pub name: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Geometry {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Window manager state of a window. Flags the backend doesn't report are false.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct WindowState {
+    pub fullscreen: bool,
+    pub maximized: bool,
+    /// Minimized or otherwise not shown.
+    pub hidden: bool,
+    /// Kept above other windows.
+    pub above: bool,
+    pub demands_attention: bool,
+}
+
+/// Kind of window as declared by the client with `_NET_WM_WINDOW_TYPE`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WindowType {
+    Normal,
+    Dialog,
+    Utility,
+    Toolbar,
+    Menu,
+    Splash,
+    Dock,
+    Desktop,
+    Notification,
+    /// Any other type, with its atom name, e.g. "_KDE_NET_WM_WINDOW_TYPE_OVERRIDE".
+    Other(String),
+}
+
+/// Process owning a window, as read from /proc.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ProcessInfo {
+    pub pid: u32,
+    pub ppid: Option<u32>,
+    /// Parent PIDs from `ppid` up to the session leader, e.g. the login shell or the
+    /// display manager session.
+    pub ancestors: Vec<u32>,
+    /// Tells apart applications sharing an executable, e.g. `python3 -m jupyter lab`.
+    pub argv: Vec<String>,
+    /// Working directory, `None` for processes of other users.
+    pub cwd: Option<String>,
+    pub uid: Option<u32>,
+    pub user: Option<String>,
+    /// Unix timestamp at which the process started.
+    pub start_time: Option<u64>,
+    /// Resident memory in bytes.
+    pub rss: Option<u64>,
+    /// User and system CPU time used so far.
+    pub cpu_time: Option<std::time::Duration>,
+}
+
+/// Application a window belongs to, from its `.desktop` file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DesktopEntry {
+    /// Desktop file id, e.g. "org.gnome.Nautilus.desktop".
+    pub id: String,
+    pub path: String,
+    /// Name in the user's language, e.g. "IntelliJ IDEA Community Edition".
+    pub name: Option<String>,
+    pub generic_name: Option<String>,
+    /// Icon name in the icon theme, or an absolute path.
+    pub icon: Option<String>,
+    /// Registered categories, e.g. ["Development", "IDE"].
+    pub categories: Vec<String>,
+    pub exec: Option<String>,
+    pub startup_wm_class: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Multiplexer {
+    Tmux,
+    Screen,
+}
+
+/// Pane (tmux) or window (screen) a terminal multiplexer client shows.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TerminalPane {
+    pub multiplexer: Multiplexer,
+    pub session: Option<String>,
+    /// PID of the shell of the pane. Not known for screen.
+    pub pid: Option<u32>,
+    /// Program running in the pane, e.g. "vim". The window title for screen.
+    pub command: Option<String>,
+    /// Arguments of that program. Empty for screen.
+    pub argv: Vec<String>,
+    /// Working directory of the program. Not known for screen.
+    pub path: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RemoteProtocol {
+    Ssh,
+    Mosh,
+    Rdp,
+    Vnc,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RemoteTarget {
+    pub protocol: RemoteProtocol,
+    /// Host name or address as given to the client, e.g. "prod-db-3".
+    pub host: String,
+    pub user: Option<String>,
+    pub port: Option<u16>,
+}
+
+/// Change of the focused window, delivered by `subscribe`.
+#[derive(Debug)]
+pub enum WindowEvent {
+    /// Another window got the focus.
+    FocusChanged(WindowInformation),
+    /// The focused window changed its title, e.g. when switching browser tabs.
+    TitleChanged(WindowInformation),
+}
+
+impl WindowEvent {
+    pub fn window(&self) -> &WindowInformation {
+        match self {
+            WindowEvent::FocusChanged(w) | WindowEvent::TitleChanged(w) => w,
+        }
+    }
+
+    pub(crate) fn window_mut(&mut self) -> &mut WindowInformation {
+        match self {
+            WindowEvent::FocusChanged(w) | WindowEvent::TitleChanged(w) => w,
+        }
+    }
+}
+
+/// Where a `BrowserTab` came from, from most to least real-time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TabSource {
+    /// Pushed by the companion browser extension to the localhost listener.
+    Extension,
+    /// Queried from the DevTools endpoint of a browser with remote debugging enabled.
+    DevTools,
+    /// Read from the address bar through the accessibility tree.
+    Accessibility,
+    /// Reconstructed from the browser session file, which is written with some delay.
+    SessionFile,
 }
 
 #[derive(Debug, Clone, PartialEq)]
+pub struct BrowserTab {
+    pub url: String,
+    pub title: Option<String>,
+    /// Name of the tab group the tab belongs to.
+    pub group: Option<String>,
+    pub pinned: Option<bool>,
+    /// Browser profile, e.g. "Default" or "Profile 1" for Chromium-based browsers.
+    pub profile: Option<String>,
+    pub incognito: Option<bool>,
+    /// Unix timestamp of the last navigation in the tab.
+    pub navigated_at: Option<u64>,
+    pub source: TabSource,
+    /// Unix timestamp at which the source last reflected the browser state.
+    /// For session files it is the file mtime, so it tells how far the file lags behind.
+    pub observed_at: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum Browser {
     Chrome,
+    Chromium,
     Opera,
     Brave,
     Edge,
@@ -21,21 +249,27 @@ pub enum Browser {
 
 impl WindowInformation {
     pub fn get_browser_type(&self) -> Option<Browser> {
-        for class_name in &self.class {
-            let class_name = class_name.to_lowercase();
-            if class_name.contains("chrome") {
-                return Some(Browser::Chrome);
-            } else if class_name.contains("firefox") {
-                return Some(Browser::Firefox);
-            } else if class_name.contains("opera") {
-                return Some(Browser::Opera);
-            } else if class_name.contains("brave") {
-                return Some(Browser::Brave);
-            } else if class_name.contains("msedge") {
-                return Some(Browser::Edge);
-            }
-        }
+        self.identify_browser().map(|id| id.browser)
+    }
+
+    /// Identify the browser owning this window, reporting which signal decided it.
+    /// See `IdentificationSignal` for the precedence.
+    pub fn identify_browser(&self) -> Option<BrowserIdentification> {
+        let desktop_id = self.pid.and_then(get_desktop_id);
+        let process_name = self.pid.and_then(get_process_name);
+        // X11 reports WM_CLASS as [instance, class], other platforms a single name
+        let (wm_class_instance, wm_class) = match self.class.as_slice() {
+            [instance, class, ..] => (Some(instance.as_str()), Some(class.as_str())),
+            [class] => (None, Some(class.as_str())),
+            [] => (None, None),
+        };
 
-        None
+        identify(&IdentificationInput {
+            desktop_id: desktop_id.as_deref(),
+            wm_class_instance,
+            wm_class,
+            exec_path: self.execpath.as_deref().filter(|p| !p.is_empty()),
+            process_name: process_name.as_deref(),
+        })
     }
 }